@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::path::PathBuf;
+
+/// Directories saved under a name, kept in `~/.gecko_bookmarks` so every session shares them
+pub struct Bookmarks {
+    // Bookmark names mapped to their directory, sorted by name
+    directories: BTreeMap<String, PathBuf>,
+}
+
+impl Bookmarks {
+    /// Loads the saved bookmarks, of which there are none if the file does not exist yet
+    pub fn load() -> Result<Self, Error> {
+        let mut bookmarks: Bookmarks = Bookmarks {
+            directories: BTreeMap::new(),
+        };
+
+        let bookmarks_file: File = match File::open(bookmarks_file()) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(bookmarks),
+            Err(e) => return Err(e),
+        };
+
+        // Each line is a name and a directory separated by a tab
+        for line in BufReader::new(bookmarks_file).lines() {
+            if let Some((name, directory)) = line?.split_once('\t') {
+                bookmarks
+                    .directories
+                    .insert(name.to_string(), PathBuf::from(directory));
+            }
+        }
+        Ok(bookmarks)
+    }
+
+    /// Saves the current directory under a name, replacing any bookmark with that name
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the bookmark
+    pub fn add(&mut self, name: &str) -> Result<(), Error> {
+        // Names cannot hold the separator of the bookmarks file
+        if name.is_empty() || name.contains(['\t', '\n']) {
+            let error_message: String = name.to_owned() + ": invalid bookmark name";
+            return Err(Error::new(ErrorKind::InvalidInput, error_message));
+        }
+
+        self.directories
+            .insert(name.to_string(), env::current_dir()?);
+        self.save()
+    }
+
+    /// Removes a bookmark
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the bookmark
+    pub fn remove(&mut self, name: &str) -> Result<(), Error> {
+        if self.directories.remove(name).is_none() {
+            return Err(not_found(name));
+        }
+        self.save()
+    }
+
+    /// Gets the directory of a bookmark
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the bookmark
+    pub fn get(&self, name: &str) -> Result<&PathBuf, Error> {
+        self.directories.get(name).ok_or_else(|| not_found(name))
+    }
+
+    /// Prints every bookmark and its directory
+    pub fn display(&self) {
+        for (name, directory) in &self.directories {
+            println!("{:<15} {}", name, directory.display());
+        }
+    }
+
+    /// Writes every bookmark to the bookmarks file
+    fn save(&self) -> Result<(), Error> {
+        let mut file: File = File::create(bookmarks_file())?;
+
+        for (name, directory) in &self.directories {
+            writeln!(file, "{}\t{}", name, directory.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Finds the file holding the bookmarks, `~/.gecko_bookmarks`
+fn bookmarks_file() -> PathBuf {
+    let home: String = env::var("HOME").unwrap_or_else(|_| String::from("."));
+    PathBuf::from(home).join(".gecko_bookmarks")
+}
+
+/// Creates the error for a bookmark that does not exist
+fn not_found(name: &str) -> Error {
+    let error_message: String = name.to_owned() + ": no such bookmark";
+    Error::new(ErrorKind::NotFound, error_message)
+}
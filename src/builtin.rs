@@ -1,68 +1,174 @@
+use crate::bookmarks::Bookmarks;
+use crate::checksum::{checksum, Algorithm};
+use crate::context::ShellContext;
+use crate::dir_stack::DirStack;
+use crate::frecency::Frecency;
 use crate::history::History;
+use crate::options::ShellOptions;
+use crate::path_cache::PathCache;
+use crate::redirect::new_command;
+use crate::sys::{current_user_name, group_name, host_name, terminal_height, user_name, RawMode};
+use crate::traps::{Traps, TRAPPABLE_SIGNALS};
+use crate::undo::{empty_trash, move_to_trash, UndoAction, UndoLog};
+use crate::utils::{
+    execute, glob_matches, has_wildcard, is_special, last_status, print_error, set_last_status,
+    unescape_wildcards,
+};
+use std::collections::HashMap;
 use std::env;
 use std::env::set_current_dir;
 use std::fs::{
-    metadata, read_dir, remove_dir_all, remove_file, File, Metadata, OpenOptions, ReadDir,
+    metadata, read_dir, read_link, remove_dir, remove_dir_all, remove_file, symlink_metadata,
+    DirEntry, File, Metadata, OpenOptions, ReadDir,
+};
+use std::io::{
+    copy, stdin, stdout, BufRead, BufReader, BufWriter, Error, ErrorKind, IsTerminal, Read,
+    StdoutLock, Write,
 };
-use std::io::{BufRead, BufReader, Error, ErrorKind};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::process::CommandExt;
 use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
 
 // Crates for correct formatting of times
 use crate::config::Config;
-use chrono::prelude::{DateTime, Local};
+use chrono::format::{Item, StrftimeItems};
+use chrono::prelude::{DateTime, Local, Utc};
+
+/// Builtins that read or change the shell's own state, which a program run in their place could
+/// not
+const SHELL_BUILTINS: [&str; 15] = [
+    "cd",
+    "pushd",
+    "popd",
+    "dirs",
+    "z",
+    "goto",
+    "bookmark",
+    "history",
+    "hash",
+    "exec",
+    "exit",
+    "set",
+    "trap",
+    "undo",
+    "empty-trash",
+];
 
 /// Handles builtins
 ///
 /// # Arguments
 ///
 /// * `commands` - A string slice representing a command and its arguments
-/// * `history` - An object that contains all previously entered commands
+/// * `context` - The shell's state, which builtins read and change
 ///
 /// # Return value
 ///
 /// True if the command was a builtin, else false.
-pub fn builtin(
-    commands: &[String],
-    mut history: &mut History,
-    config: &Config,
-) -> Result<bool, Error> {
+pub fn builtin(commands: &[String], context: &mut ShellContext) -> Result<bool, Error> {
+    let ShellContext {
+        history,
+        options,
+        path_cache,
+        traps,
+        undo_log,
+        dir_stack,
+        config,
+        ..
+    } = context;
+
+    if runs_as_program(commands, path_cache) {
+        return Ok(false);
+    }
+
+    // Builtins record a status other than 0 themselves, such as 'diff' finding differences
+    let previous_status: i32 = last_status();
+    set_last_status(0);
+
     match &commands.first().unwrap_or(&String::new())[..] {
         "ls" => {
             if let Err(e) = list_files_builtin(commands, config) {
-                eprintln!(
-                    "\x1b[38;2;255;0;0mError: Could not list contents\n{}\x1b[0m",
-                    e
-                );
+                print_error("Could not list contents", &e, config);
                 return Err(e);
             }
             Ok(true)
         }
         "rm" => {
-            if let Err(e) = file_remove_builtin(commands) {
-                eprintln!(
-                    "\x1b[38;2;255;0;0mError: Could not remove file/directory\n{}\x1b[0m",
-                    e
-                );
+            if let Err(e) = file_remove_builtin(commands, undo_log, config) {
+                print_error("Could not remove file/directory", &e, config);
                 return Err(e);
             }
             Ok(true)
         }
         "touch" => {
-            if let Err(e) = touch_builtin(commands) {
-                eprintln!(
-                    "\x1b[38;2;255;0;0mError: Could not create file\n{}\x1b[0m",
-                    e
-                );
+            if let Err(e) = touch_builtin(commands, undo_log) {
+                print_error("Could not create file", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "empty-trash" => {
+            if let Err(e) = empty_trash_builtin(commands) {
+                print_error("Could not empty the trash", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "undo" => {
+            if let Err(e) = undo_builtin(commands, undo_log) {
+                print_error("Could not undo", &e, config);
                 return Err(e);
             }
             Ok(true)
         }
         "cd" => {
-            if let Err(e) = change_dir_builtin(commands) {
-                eprintln!(
-                    "\x1b[38;2;255;0;0mError: Could not change directories\n{}\x1b[0m",
-                    e
-                );
+            if let Err(e) = change_dir_builtin(commands, history, config) {
+                print_error("Could not change directories", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "pushd" => {
+            if let Err(e) = push_dir_builtin(commands, history, dir_stack, config) {
+                print_error("Could not change directories", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "popd" => {
+            if let Err(e) = pop_dir_builtin(commands, history, dir_stack, config) {
+                print_error("Could not change directories", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "dirs" => {
+            if let Err(e) = dirs_builtin(commands, dir_stack) {
+                print_error("Could not list directory stack", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "z" => {
+            if let Err(e) = jump_builtin(commands, history, config) {
+                print_error("Could not change directories", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "goto" => {
+            if let Err(e) = goto_builtin(commands, history, config) {
+                print_error("Could not change directories", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "bookmark" => {
+            if let Err(e) = bookmark_builtin(commands) {
+                print_error("Could not access bookmarks", &e, config);
                 return Err(e);
             }
             Ok(true)
@@ -72,31 +178,171 @@ pub fn builtin(
             Ok(true)
         }
         "history" => {
-            if let Err(e) = history_builtin(commands, &mut history) {
-                eprintln!(
-                    "\x1b[38;2;255;0;0mError: Could not display history\n{}\x1b[0m",
-                    e
-                );
+            if let Err(e) = history_builtin(commands, history) {
+                print_error("Could not display history", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "hash" => {
+            if let Err(e) = hash_builtin(commands, path_cache) {
+                print_error("Could not access the command table", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "exec" => {
+            if let Err(e) = exec_builtin(commands, history, path_cache, config) {
+                print_error("Could not replace the shell", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "exit" => {
+            if let Err(e) = exit_builtin(commands, previous_status) {
+                print_error("Could not exit", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "nice" => {
+            if let Err(e) = nice_builtin(commands, options, path_cache, config) {
+                print_error("Could not run with the given priority", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "set" => {
+            if let Err(e) = set_builtin(commands, options) {
+                print_error("Could not change shell options", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "trap" => {
+            if let Err(e) = trap_builtin(commands, traps) {
+                print_error("Could not set trap", &e, config);
                 return Err(e);
             }
             Ok(true)
         }
         "clear" => {
             if let Err(e) = clear_builtin(commands) {
-                eprintln!(
-                    "\x1b[38;2;255;0;0mError: Could not clear the screen\n{}\x1b[0m",
-                    e
-                );
+                print_error("Could not clear the screen", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "diff" => {
+            if let Err(e) = diff_builtin(commands) {
+                print_error("Could not compare files", &e, config);
+                // As diff(1) does, 1 is kept for files that differ
+                set_last_status(2);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "more" | "less" => {
+            if let Err(e) = pager_builtin(commands) {
+                print_error("Could not page through contents", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "date" => {
+            if let Err(e) = date_builtin(commands) {
+                print_error("Could not display the date", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "whoami" => {
+            if let Err(e) = whoami_builtin(commands) {
+                print_error("Could not find the current user", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "hostname" => {
+            if let Err(e) = hostname_builtin(commands) {
+                print_error("Could not find the host name", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "echo" => {
+            if let Err(e) = echo_builtin(commands) {
+                print_error("Could not print arguments", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "rev" => {
+            if let Err(e) = rev_builtin(commands) {
+                print_error("Could not reverse lines", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "tac" => {
+            if let Err(e) = tac_builtin(commands) {
+                print_error("Could not display file contents", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "nl" => {
+            if let Err(e) = number_lines_builtin(commands) {
+                print_error("Could not number lines", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "column" => {
+            if let Err(e) = column_builtin(commands) {
+                print_error("Could not format columns", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "strings" => {
+            if let Err(e) = strings_builtin(commands) {
+                print_error("Could not find strings", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "file" => {
+            if let Err(e) = file_type_builtin(commands) {
+                print_error("Could not determine file type", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "md5sum" | "sha256sum" => {
+            if let Err(e) = checksum_builtin(commands) {
+                print_error("Could not compute checksums", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "paste" => {
+            if let Err(e) = paste_builtin(commands) {
+                print_error("Could not merge lines", &e, config);
+                return Err(e);
+            }
+            Ok(true)
+        }
+        "split" => {
+            if let Err(e) = split_builtin(commands) {
+                print_error("Could not split file", &e, config);
                 return Err(e);
             }
             Ok(true)
         }
         "cat" => {
-            if let Err(e) = display_file_contents(commands) {
-                eprintln!(
-                    "\x1b[38;2;255;0;0mError: Could not display file contents\n{}\x1b[0m",
-                    e
-                );
+            if let Err(e) = display_file_contents(commands, config) {
+                print_error("Could not display file contents", &e, config);
                 return Err(e);
             }
             Ok(true)
@@ -105,278 +351,3090 @@ pub fn builtin(
     }
 }
 
+/// Checks whether a builtin's line should run the program of the same name instead. Builtins
+/// cannot redirect or pipe, so a line that does runs the program on PATH that the builtin stands
+/// in for, unless the builtin works on the shell itself.
+///
+/// # Arguments
+///
+/// * `commands` - The command, its arguments, and any redirections
+/// * `path_cache` - Cached locations of commands on PATH
+fn runs_as_program(commands: &[String], path_cache: &mut PathCache) -> bool {
+    let name: &str = match commands.first() {
+        Some(name) => name,
+        None => return false,
+    };
+
+    commands.iter().any(|token| is_special(token))
+        && !SHELL_BUILTINS.contains(&name)
+        && path_cache.is_on_path(name)
+}
+
+/// Usage message for the 'ls' builtin
+const LS_USAGE: &str =
+    "Usage: ls [-a] [-i] [-l] [-R] [-L|-P] [--author] [--full-time] [--git] [--icons] [-X|--sort=extension] [--no-headers] [--] <directory1 directory2 ...>";
+
+/// Flags given to the 'ls' builtin
+#[derive(Default)]
+struct ListOptions {
+    // Show entries whose names start with '.'
+    all: bool,
+    // Show the inode number of each entry
+    inode: bool,
+    // List subdirectories, and theirs, after each directory
+    recursive: bool,
+    // Show the permissions and size of each entry
+    long: bool,
+    // Show the owner and group of each entry
+    author: bool,
+    // Show modified times with seconds and the timezone
+    full_time: bool,
+    // Describe what symlinks point to rather than the links themselves
+    follow_symlinks: bool,
+    // Show the git status of each entry
+    git: bool,
+    // Show an icon for the type of each entry
+    icons: bool,
+    // List entries grouped by extension, then by name
+    sort_extension: bool,
+    // List every directory in one table, naming entries by their path instead of under a header
+    no_headers: bool,
+    // Icons by extension, or "dir", "exec", or "default", from the 'ls_icons' setting
+    icon_map: HashMap<String, String>,
+    // Git status codes of the changed paths in the listing's repository, by full path, or `None`
+    // if the listing is not in a repository
+    git_statuses: Option<HashMap<PathBuf, String>>,
+}
+
+/// Parses the flags given to the 'ls' builtin
+///
+/// # Arguments
+///
+/// * `flags` - The flags given to 'ls'. Short flags may be combined, e.g. `-ab`
+fn parse_list_options(flags: &[&str]) -> Result<ListOptions, Error> {
+    let mut options: ListOptions = ListOptions::default();
+
+    for flag in flags {
+        // Long flags are matched whole
+        if flag.starts_with("--") {
+            match *flag {
+                "--all" => options.all = true,
+                "--inode" => options.inode = true,
+                "--recursive" => options.recursive = true,
+                "--author" => options.author = true,
+                "--full-time" => options.full_time = true,
+                "--follow-symlinks" => options.follow_symlinks = true,
+                "--git" => options.git = true,
+                "--icons" => options.icons = true,
+                "--sort=extension" => options.sort_extension = true,
+                "--no-headers" => options.no_headers = true,
+                _ => return Err(invalid_list_option(flag)),
+            }
+        }
+        // Short flags are matched one character at a time
+        else {
+            for short_flag in flag[1..].chars() {
+                match short_flag {
+                    'a' => options.all = true,
+                    'i' => options.inode = true,
+                    'R' => options.recursive = true,
+                    'l' => options.long = true,
+                    'L' => options.follow_symlinks = true,
+                    'P' => options.follow_symlinks = false,
+                    'X' => options.sort_extension = true,
+                    _ => return Err(invalid_list_option(flag)),
+                }
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// Creates the error for an unknown 'ls' flag
+fn invalid_list_option(flag: &str) -> Error {
+    let error_message: String = "Invalid option ".to_owned() + flag + "\n" + LS_USAGE;
+    Error::new(ErrorKind::InvalidInput, error_message)
+}
+
 /// Implements a built-in version of the 'ls' command.
 ///
 /// # Arguments
 ///
 /// * `args` - A vector of strings corresponding to the command and its arguments.
 fn list_files_builtin(args: &[String], config: &Config) -> Result<(), Error> {
+    // Flags and directories given as arguments
+    let (flags, directories) = split_flags(&args[1..]);
+
+    let mut options: ListOptions = parse_list_options(&flags)?;
+
+    if options.icons {
+        options.icon_map = parse_icon_map(&config.get("ls_icons"));
+    }
+
+    // A combined listing has its column names once, at the top
+    if options.no_headers {
+        print_listing_header(&options);
+    }
+
     // If no directories were given
-    if args.len() == 1 {
+    if directories.is_empty() {
         // Get all paths in the current directory
         let paths: ReadDir = read_dir(".")?;
 
+        if options.git {
+            options.git_statuses = git_statuses(Path::new("."));
+        }
+
         // Print contents of current directory
-        print_directory_contents(paths, config)?;
-        println!()
+        print_directory_contents(paths, &options, config)?;
+        end_listing(&options);
+
+        if options.recursive {
+            list_subdirectories(Path::new("."), &mut options, config)?;
+        }
     }
     // If ls was given multiple directories as an argument
-    else if args.len() > 1 {
-        // Array of directories given as arguments
-        let directories: &[String] = &args[1..args.len()];
-
+    else {
         // Loop through directories
         for directory in directories {
-            // Check if directory exists
-            let valid_directory: bool = Path::new(directory).exists();
+            // Check if directory exists, without following a symlink to something missing
+            let valid_directory: bool = symlink_metadata(directory).is_ok();
+
+            if !valid_directory {
+                let error_message: String = "Directory ".to_owned() + directory + " does not exist";
+                return Err(Error::other(error_message));
+            }
 
-            if valid_directory == true {
+            // Directories, and symlinks to directories, are listed by their contents
+            if metadata(directory)
+                .map(|target| target.is_dir())
+                .unwrap_or(false)
+            {
                 // Get all paths that exists in the given directory
                 let paths: ReadDir = read_dir(directory)?;
 
-                // Length of right side of directory header
-                let lhs_width: usize = (60 - directory.len()) / 2;
-
-                // Length of left side of directory header
-                let rhs_width: usize = if (lhs_width % 2) == 0 {
-                    lhs_width + 1
-                } else {
-                    lhs_width
-                };
+                if options.git {
+                    options.git_statuses = git_statuses(Path::new(directory));
+                }
 
                 // Print contents of directory
-                println!(
-                    "{}[{}]{}",
-                    "-".repeat(lhs_width),
-                    directory,
-                    "-".repeat(rhs_width)
-                );
-                print_directory_contents(paths, config)?;
-                println!()
-            } else {
-                let error_message: String = "Directory ".to_owned() + directory + " does not exist";
-                return Err(Error::new(ErrorKind::Other, error_message));
+                print_directory_header(directory, &options);
+                print_directory_contents(paths, &options, config)?;
+
+                if options.recursive {
+                    end_listing(&options);
+                    list_subdirectories(Path::new(directory), &mut options, config)?;
+                    continue;
+                }
+            }
+            // Files, and symlinks to files, are listed as a single entry named as given
+            else {
+                if options.git {
+                    options.git_statuses = git_statuses(&parent_directory(Path::new(directory)));
+                }
+
+                if !options.no_headers {
+                    print_listing_header(&options);
+                }
+                print_entry(directory, directory, &options, config)?;
             }
+            end_listing(&options);
         }
     }
+
+    if options.no_headers {
+        println!();
+    }
     Ok(())
 }
 
-/// Handles printing and styling all the given paths
-fn print_directory_contents(paths: ReadDir, config: &Config) -> Result<(), Error> {
-    // Displaying content prompts
-    println!("{:19}  {:41}", "Modified", "Name");
-    println!("{:19}  {:41}", "-".repeat(19), "-".repeat(41));
-
-    for path in paths {
-        // Path for file
-        let path_str: String = path?.path().display().to_string();
-
-        // Metadata for the file
-        let file_metadata: Metadata = metadata(&path_str)?;
-
-        // Last modified time for a file in local time
-        let file_modified_time: DateTime<Local> = file_metadata.modified()?.into();
-
-        // If file is a directory
-        if PathBuf::from(&path_str).is_dir() {
-            // println!("\x1b[38;2;42;125;211mError\x1b[0m");
-            let directory_name: String = "\x1b[38;2;".to_owned()
-                + &config.get("directory_text_color")
-                + "m"
-                + &path_str.split("/").collect::<Vec<&str>>()[1].to_owned()
-                + "/\x1b[0m";
-
-            println!(
-                "{:<19}  {:<41}",
-                file_modified_time.format("%m-%d-%Y %I:%M %p"),
-                directory_name
-            );
-        } else {
-            let file_name = "\x1b[38;2;".to_owned()
-                + &config.get("filename_text_color")
-                + "m"
-                + path_str.split("/").collect::<Vec<&str>>()[1]
-                + "\x1b[0m";
-            println!(
-                "{:<19}  {:<41}",
-                file_modified_time.format("%m-%d-%Y %I:%M %p"),
-                file_name
-            );
-        }
+/// Prints the blank line after the listing of a directory, which a combined listing goes without
+fn end_listing(options: &ListOptions) {
+    if !options.no_headers {
+        println!();
     }
-    Ok(())
 }
 
-/// Implements a built-in version of the 'rm' command.
+/// Prints the line naming a directory above its listing, unless listings are combined
 ///
 /// # Arguments
 ///
-/// * `args` - A vector of strings corresponding to the command and its arguments.
-fn file_remove_builtin(args: &[String]) -> Result<(), Error> {
-    // If no arguments are found
-    if args.len() < 2 {
-        return Err(Error::new(
-            ErrorKind::InvalidInput,
-            "usage: rm [-r] <file1 file2 ...>",
-        ));
-    }
-    // If '-r' flag is found
-    else if args[1] == "-r" {
-        for directory in &args[2..] {
-            remove_dir_all(directory)?;
-        }
+/// * `directory` - The path of the directory
+/// * `options` - The flags given to 'ls'
+fn print_directory_header(directory: &str, options: &ListOptions) {
+    if options.no_headers {
+        return;
     }
-    // Remove all files listed
-    else {
-        for file in &args[1..] {
-            remove_file(file)?;
+
+    // Length of right side of directory header, with none for paths too long to fit
+    let lhs_width: usize = 60_usize.saturating_sub(directory.len()) / 2;
+
+    // Length of left side of directory header
+    let rhs_width: usize = if lhs_width.is_multiple_of(2) {
+        lhs_width + 1
+    } else {
+        lhs_width
+    };
+
+    println!(
+        "{}[{}]{}",
+        "-".repeat(lhs_width),
+        directory,
+        "-".repeat(rhs_width)
+    );
+}
+
+/// Lists each subdirectory of a directory under its own header, followed by its subdirectories,
+/// depth first and in name order. Subdirectories that cannot be read are reported and skipped.
+///
+/// # Arguments
+///
+/// * `directory` - The directory whose subdirectories are listed
+/// * `options` - The flags given to 'ls'
+/// * `config` - The shell's settings, used for colors
+fn list_subdirectories(
+    directory: &Path,
+    options: &mut ListOptions,
+    config: &Config,
+) -> Result<(), Error> {
+    // Symlinks to directories are not followed, so a link cannot make the listing loop forever
+    let mut subdirectories: Vec<PathBuf> = read_dir(directory)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .map(|entry| entry.path())
+        .filter(|path| options.all || !is_hidden(path))
+        .collect();
+    subdirectories.sort();
+
+    for subdirectory in subdirectories {
+        print_directory_header(&subdirectory.display().to_string(), options);
+
+        let paths: ReadDir = match read_dir(&subdirectory) {
+            Ok(paths) => paths,
+            Err(e) => {
+                let message: String = format!("Could not list {}", subdirectory.display());
+                print_error(&message, &e, config);
+                end_listing(options);
+                continue;
+            }
+        };
+
+        if options.git {
+            options.git_statuses = git_statuses(&subdirectory);
         }
+
+        print_directory_contents(paths, options, config)?;
+        end_listing(options);
+        list_subdirectories(&subdirectory, options, config)?;
     }
     Ok(())
 }
 
-/// Implements a built-in version of the 'touch' command.
+/// Checks whether an entry is hidden, which is when its name starts with '.'
 ///
 /// # Arguments
 ///
-/// * `args` - A vector of strings corresponding to the command and its arguments.
-fn touch_builtin(args: &[String]) -> Result<(), Error> {
-    // If no arguments are given
-    if args.len() <= 1 {
-        return Err(Error::new(
-            ErrorKind::InvalidInput,
-            "usage: touch <file1 file2 ...>",
-        ));
+/// * `path` - The path of the entry
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Gets the strftime format and width of the modified time column
+fn time_column(options: &ListOptions) -> (&'static str, usize) {
+    if options.full_time {
+        ("%Y-%m-%d %H:%M:%S %z", 25)
+    } else {
+        ("%m-%d-%Y %I:%M %p", 19)
     }
+}
 
-    //the loop will ignore the first element in the string array as it will be "touch"
-    for file_path in &args[1..] {
-        // File to be created or have its time updated
-        let file: &Path = Path::new(file_path);
+/// Prints the column names of a listing
+fn print_listing_header(options: &ListOptions) {
+    let (_, time_width): (&str, usize) = time_column(options);
 
-        //if the file already exists we add a new line to the file, and immediately remove it
-        if file.exists() {
-            let file_to_change: File = OpenOptions::new().append(true).write(true).open(file)?;
+    // Displaying content prompts
+    if options.inode {
+        print!("{:>10}  ", "Inode");
+    }
+    if options.long {
+        print!("{:11}  ", "Permissions");
+    }
+    if options.author {
+        print!("{:12}  {:12}  ", "Owner", "Group");
+    }
+    if options.long {
+        print!("{:>12}  ", "Size");
+    }
+    print!("{:width$}  ", "Modified", width = time_width);
+    if options.git_statuses.is_some() {
+        print!("{:3}  ", "Git");
+    }
+    println!("{:width$}", "Name", width = NAME_WIDTH);
+    if options.inode {
+        print!("{:>10}  ", "-".repeat(10));
+    }
+    if options.long {
+        print!("{:11}  ", "-".repeat(11));
+    }
+    if options.author {
+        print!("{:12}  {:12}  ", "-".repeat(12), "-".repeat(12));
+    }
+    if options.long {
+        print!("{:>12}  ", "-".repeat(12));
+    }
+    print!("{:width$}  ", "-".repeat(time_width), width = time_width);
+    if options.git_statuses.is_some() {
+        print!("{:3}  ", "-".repeat(3));
+    }
+    println!("{:width$}", "-".repeat(NAME_WIDTH), width = NAME_WIDTH);
+}
 
-            //get metadata to access for adding and removing new lines
-            let metadata: Metadata = file.metadata()?;
+/// Handles printing and styling all the given paths
+///
+/// Entries are printed as they are pulled from `paths`, so passing a `ReadDir` directly streams
+/// the listing without buffering the whole directory. Only listings that need sorting should
+/// collect their entries first.
+fn print_directory_contents<I>(
+    paths: I,
+    options: &ListOptions,
+    config: &Config,
+) -> Result<(), Error>
+where
+    I: IntoIterator<Item = Result<DirEntry, Error>>,
+{
+    if !options.no_headers {
+        print_listing_header(options);
+    }
 
-            // adds new line
-            file_to_change.set_len(metadata.len() + 1)?;
+    // Hidden entries are left out unless '-a' is given
+    let paths = paths
+        .into_iter()
+        .map(|path| path.map(|entry| entry.path()))
+        .filter(|path| options.all || !path.as_ref().is_ok_and(|path| is_hidden(path)));
 
-            // removes new line
-            file_to_change.set_len(file.metadata()?.len() - 1)?;
+    let print_path = |path: PathBuf| -> Result<(), Error> {
+        print_entry(
+            &path.display().to_string(),
+            &entry_name(&path, options),
+            options,
+            config,
+        )
+    };
+
+    if options.sort_extension {
+        let mut sorted_paths: Vec<PathBuf> = paths.collect::<Result<Vec<PathBuf>, Error>>()?;
+
+        // Entries without an extension come first, as in coreutils
+        sorted_paths.sort_by(|a, b| {
+            a.extension()
+                .cmp(&b.extension())
+                .then_with(|| a.file_name().cmp(&b.file_name()))
+        });
+
+        for path in sorted_paths {
+            print_path(path)?;
         }
-        //if the file does not exist, create it
-        else {
-            File::create(&file)?;
+    } else {
+        for path in paths {
+            print_path(path?)?;
         }
     }
     Ok(())
 }
 
-/// Implements a built-in version of the 'cd' command.
+/// Finds the name an entry of a directory's listing is shown with
 ///
 /// # Arguments
 ///
-/// * `args` - A vector of strings corresponding to the command and its arguments.
-fn change_dir_builtin(args: &[String]) -> Result<(), Error> {
-    // If no arguments are given
-    if args.len() == 1 {
-        return Err(Error::new(
-            ErrorKind::InvalidInput,
-            "Usage: cd <directory path>",
-        ));
+/// * `path` - The path of the entry
+/// * `options` - The flags given to 'ls'
+///
+/// # Return value
+///
+/// The entry's last component, however deep the listed directory is, or its whole path in a
+/// combined listing
+fn entry_name(path: &Path, options: &ListOptions) -> String {
+    let path_str: String = path.display().to_string();
+
+    if options.no_headers {
+        path_str.strip_prefix("./").unwrap_or(&path_str).to_string()
+    } else {
+        path.file_name()
+            .map_or(path_str.clone(), |name| name.to_string_lossy().to_string())
     }
+}
 
-    // If the given path is a valid directory
-    return if PathBuf::from(&args[1]).is_dir() == true {
-        set_current_dir(&args[1])
+/// Prints and styles a single row of a listing
+///
+/// # Arguments
+///
+/// * `path_str` - The path of the entry
+/// * `name` - The name the entry is displayed with
+/// * `options` - The flags given to 'ls'
+/// * `config` - The shell's settings, used for colors
+fn print_entry(
+    path_str: &str,
+    name: &str,
+    options: &ListOptions,
+    config: &Config,
+) -> Result<(), Error> {
+    let (time_format, time_width): (&str, usize) = time_column(options);
+
+    // Metadata for the file, or for what it links to when following symlinks
+    let file_metadata: Metadata = entry_metadata(Path::new(path_str), options)?;
+
+    // Last modified time for a file in local time
+    let file_modified_time: DateTime<Local> = file_metadata.modified()?.into();
+
+    if options.inode {
+        print!("{:>10}  ", file_metadata.ino());
+    }
+    if options.long {
+        print!("{:11}  ", permissions_string(file_metadata.mode()));
+    }
+    if options.author {
+        // Ids without a name are shown as numbers
+        let owner: String =
+            user_name(file_metadata.uid()).unwrap_or_else(|| file_metadata.uid().to_string());
+        let group: String =
+            group_name(file_metadata.gid()).unwrap_or_else(|| file_metadata.gid().to_string());
+
+        print!("{:12}  {:12}  ", owner, group);
+    }
+    if options.long {
+        print!("{:>12}  ", file_metadata.size());
+    }
+
+    print!(
+        "{:<width$}  ",
+        file_modified_time.format(time_format),
+        width = time_width
+    );
+
+    if let Some(statuses) = &options.git_statuses {
+        // Entries are looked up by full path, without following a symlink entry itself
+        let path: &Path = Path::new(path_str);
+        let full_path: Option<PathBuf> = parent_directory(path)
+            .canonicalize()
+            .ok()
+            .zip(path.file_name())
+            .map(|(parent, name)| parent.join(name));
+
+        let status: &str = full_path
+            .and_then(|full_path| statuses.get(&full_path))
+            .map_or("", String::as_str);
+
+        print!("{:3}  ", status);
+    }
+
+    if options.icons {
+        print!("{} ", entry_icon(name, &file_metadata, &options.icon_map));
+    }
+
+    // Directories are marked with a trailing '/'
+    let (color, name): (String, String) = if file_metadata.is_dir() {
+        (config.get("directory_text_color"), name.to_owned() + "/")
     } else {
-        let error_message: String = args[1].to_owned() + " is not a valid directory";
-        Err(Error::new(ErrorKind::Other, error_message))
+        (config.get("filename_text_color"), name.to_owned())
     };
+
+    for (index, line) in fit_name(&name, &config.get("ls_long_names"))
+        .iter()
+        .enumerate()
+    {
+        // Lines a name is wrapped onto start under the name column
+        if index > 0 {
+            print!("{:width$}", "", width = name_column_start(options));
+        }
+
+        let styled_line: String = "\x1b[38;2;".to_owned() + &color + "m" + line + "\x1b[0m";
+        println!("{:<width$}", styled_line, width = NAME_WIDTH);
+    }
+    Ok(())
 }
 
-/// Implements a built-in version of the 'pwd' command.
-fn pwd_builtin() {
-    println!(
-        "{}",
-        env::current_dir()
-            .expect("Error: Could not access current directory env")
-            .display()
-    )
+/// Reads the metadata an entry of a listing is described by
+///
+/// # Arguments
+///
+/// * `path` - The path of the entry
+/// * `options` - The flags given to 'ls'
+///
+/// # Return value
+///
+/// The metadata of what a symlink points to when following symlinks, else of the entry itself.
+/// A symlink pointing to nothing is described by its own metadata either way.
+fn entry_metadata(path: &Path, options: &ListOptions) -> Result<Metadata, Error> {
+    if options.follow_symlinks {
+        metadata(path).or_else(|_| symlink_metadata(path))
+    } else {
+        symlink_metadata(path)
+    }
 }
 
-/// Implements a built-in command history
+/// Width of the name column of a listing
+const NAME_WIDTH: usize = 41;
+
+/// Fits a name longer than the name column as the 'ls_long_names' setting asks: split onto
+/// several lines when "wrap", or cut short and ended with '…' when "ellipsize". Otherwise the name
+/// is kept whole and runs past the column.
 ///
 /// # Arguments
 ///
-/// * `args` - A vector of strings corresponding to the command and its arguments.
-/// * `history` - An object that contains all previously entered commands
-fn history_builtin(args: &[String], history: &mut History) -> Result<(), Error> {
-    // If no arguments are given
-    if args.len() == 1 {
-        // print all history
-        history.display_full_history();
+/// * `name` - The name an entry is displayed with
+/// * `mode` - The value of the 'ls_long_names' setting
+///
+/// # Return value
+///
+/// The lines to display the name on
+fn fit_name(name: &str, mode: &str) -> Vec<String> {
+    let characters: Vec<char> = name.chars().collect();
+
+    if characters.len() <= NAME_WIDTH {
+        return vec![name.to_string()];
     }
-    // If two arguments are given
-    else if args.len() == 2 {
-        // Check if the received argument is a number
-        match &args[1].parse::<usize>() {
-            Ok(ok) => history.display_num_commands(*ok),
-            Err(_) => {
-                return Err(Error::new(
-                    ErrorKind::InvalidInput,
-                    "Non-number argument given",
-                ))
+
+    match mode {
+        "wrap" => characters
+            .chunks(NAME_WIDTH)
+            .map(|line| line.iter().collect())
+            .collect(),
+        "ellipsize" => vec![characters[..NAME_WIDTH - 1].iter().collect::<String>() + "…"],
+        _ => vec![name.to_string()],
+    }
+}
+
+/// Gets how far into a row of a listing the name column starts
+fn name_column_start(options: &ListOptions) -> usize {
+    let (_, time_width): (&str, usize) = time_column(options);
+
+    // Each column is followed by two spaces
+    let mut start: usize = time_width + 2;
+    if options.inode {
+        start += 10 + 2;
+    }
+    if options.long {
+        start += 11 + 2 + 12 + 2;
+    }
+    if options.author {
+        start += 12 + 2 + 12 + 2;
+    }
+    if options.git_statuses.is_some() {
+        start += 3 + 2;
+    }
+    if options.icons {
+        // The icon and the space after it
+        start += 2;
+    }
+    start
+}
+
+/// Formats the type and permission bits of a file mode the way 'ls -l' does, e.g. `drwxr-xr-x`
+///
+/// # Arguments
+///
+/// * `mode` - The file's mode, from `st_mode`
+fn permissions_string(mode: u32) -> String {
+    let file_type: char = match mode & libc::S_IFMT {
+        libc::S_IFDIR => 'd',
+        libc::S_IFLNK => 'l',
+        libc::S_IFCHR => 'c',
+        libc::S_IFBLK => 'b',
+        libc::S_IFIFO => 'p',
+        libc::S_IFSOCK => 's',
+        _ => '-',
+    };
+
+    // Read, write, and execute for the owner, group, and others, where the setuid, setgid, and
+    // sticky bits take the place of execute
+    let classes: [(u32, u32, char); 3] = [
+        (6, libc::S_ISUID, 's'),
+        (3, libc::S_ISGID, 's'),
+        (0, libc::S_ISVTX, 't'),
+    ];
+
+    let mut permissions: String = file_type.to_string();
+    for (shift, special_bit, special_char) in classes {
+        let bits: u32 = (mode >> shift) & 0o7;
+        let special: bool = mode & special_bit != 0;
+
+        permissions.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        permissions.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        permissions.push(match (bits & 0o1 != 0, special) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    permissions
+}
+
+/// Parses the 'ls_icons' setting, a list of `<key>=<icon>` separated by commas
+///
+/// # Arguments
+///
+/// * `setting` - The value of the setting
+fn parse_icon_map(setting: &str) -> HashMap<String, String> {
+    setting
+        .split(',')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, icon)| (key.trim().to_lowercase(), icon.trim().to_string()))
+        .collect()
+}
+
+/// Chooses the icon of a listing entry, by whether it is a directory, then by its extension, then
+/// by whether it is executable
+///
+/// # Arguments
+///
+/// * `name` - The name of the entry
+/// * `file_metadata` - The entry's metadata
+/// * `icon_map` - Icons by extension, or "dir", "exec", or "default"
+fn entry_icon<'a>(
+    name: &str,
+    file_metadata: &Metadata,
+    icon_map: &'a HashMap<String, String>,
+) -> &'a str {
+    let extension: Option<String> = Path::new(name)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+
+    let key: Option<&str> = if file_metadata.is_dir() {
+        Some("dir")
+    } else {
+        extension.as_deref()
+    };
+
+    key.and_then(|key| icon_map.get(key))
+        .or_else(|| {
+            // Executables without a known extension
+            if file_metadata.is_file() && file_metadata.mode() & 0o111 != 0 {
+                icon_map.get("exec")
+            } else {
+                None
             }
+        })
+        .or_else(|| icon_map.get("default"))
+        .map_or(" ", String::as_str)
+}
+
+/// Gets the directory a path is in, which is "." for a bare file name
+fn parent_directory(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Finds the git status of every changed path in the repository a directory is in
+///
+/// # Arguments
+///
+/// * `directory` - A directory in the repository
+///
+/// # Return value
+///
+/// The two letter status codes of `git status --porcelain` by full path, where directories have
+/// the status of the first changed path in them, or `None` if the directory is not in a
+/// repository or git cannot be run
+fn git_statuses(directory: &Path) -> Option<HashMap<PathBuf, String>> {
+    // Paths in the status are relative to the top of the repository
+    let top_level: Output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(["rev-parse", "--show-toplevel"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !top_level.status.success() {
+        return None;
+    }
+    let top_level: PathBuf = PathBuf::from(String::from_utf8_lossy(&top_level.stdout).trim_end())
+        .canonicalize()
+        .ok()?;
+
+    let status: Output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(["status", "--porcelain", "-z"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !status.status.success() {
+        return None;
+    }
+
+    let mut statuses: HashMap<PathBuf, String> = HashMap::new();
+
+    // Each entry is "XY path", and renames and copies are followed by the original path
+    let mut entries = status.stdout.split(|byte| *byte == 0);
+    while let Some(entry) = entries.next() {
+        let entry: String = String::from_utf8_lossy(entry).to_string();
+        if entry.len() < 4 {
+            continue;
         }
-    } else {
+
+        let code: &str = &entry[..2];
+        if code.contains('R') || code.contains('C') {
+            entries.next();
+        }
+
+        // The changed path and every directory above it in the repository
+        let changed: PathBuf = top_level.join(&entry[3..]);
+        for path in changed.ancestors().take_while(|path| *path != top_level) {
+            statuses
+                .entry(path.to_path_buf())
+                .or_insert_with(|| code.to_string());
+        }
+    }
+    Some(statuses)
+}
+
+/// Implements a built-in version of the 'rm' command.
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `undo_log` - How to reverse the destructive builtins that have run
+/// * `config` - The shell's settings, which choose whether files are moved to the trash
+fn file_remove_builtin(
+    args: &[String],
+    undo_log: &mut UndoLog,
+    config: &Config,
+) -> Result<(), Error> {
+    // Flags and files given as arguments
+    let (flags, files) = split_flags(&args[1..]);
+
+    // If no files are found or an unknown flag is given
+    if files.is_empty()
+        || flags
+            .iter()
+            .any(|flag| *flag != "-r" && *flag != "--one-file-system")
+    {
         return Err(Error::new(
             ErrorKind::InvalidInput,
-            "Usage: history <num of previous commands>",
+            "usage: rm [-r] [--one-file-system] [--] <file1 file2 ...>",
         ));
     }
+
+    // If files should be moved to the trash, where 'undo' can restore them from
+    if config.get("rm_trash") == "true" {
+        // Files moved so far, with where they were moved from
+        let mut trashed: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+        let result: Result<(), Error> = trash_files(&files, flags.contains(&"-r"), &mut trashed);
+
+        // Files moved before an error can still be restored
+        if !trashed.is_empty() {
+            undo_log.record(UndoAction::Restore(trashed));
+        }
+        return result;
+    }
+
+    // Files removed for good cannot be restored, so 'undo' must not reverse an older builtin in
+    // their place
+    undo_log.clear();
+
+    // If removing recursively without leaving the filesystem of each directory
+    if flags.contains(&"-r") && flags.contains(&"--one-file-system") {
+        // Whether anything was left behind because it is on another filesystem
+        let mut skipped: bool = false;
+
+        for directory in files {
+            // Device of the filesystem the directory is on
+            let device: u64 = symlink_metadata(directory)?.dev();
+
+            if !remove_on_device(Path::new(directory), device)? {
+                skipped = true;
+            }
+        }
+
+        // As in GNU rm, skipping anything is a failure even though the rest was removed
+        if skipped {
+            return Err(Error::other(
+                "Some entries were skipped, since they are on a different device",
+            ));
+        }
+    }
+    // If '-r' flag is found
+    else if flags.contains(&"-r") {
+        for directory in files {
+            remove_dir_all(directory)?;
+        }
+    }
+    // Remove all files listed
+    else {
+        for file in files {
+            remove_file(file)?;
+        }
+    }
     Ok(())
 }
 
-/// Implements a built-in command 'clear'
-fn clear_builtin(args: &[String]) -> Result<(), Error> {
-    // If too many arguments are given
+/// Moves files to the trash instead of removing them
+///
+/// # Arguments
+///
+/// * `files` - The files to move
+/// * `recursive` - Whether directories may be moved
+/// * `trashed` - The files that have been moved, each with where it was moved from
+fn trash_files(
+    files: &[&str],
+    recursive: bool,
+    trashed: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<(), Error> {
+    for file in files {
+        // Directories need '-r' as they do without the trash
+        if !recursive && symlink_metadata(file)?.is_dir() {
+            let error_message: String = file.to_string() + " is a directory";
+            return Err(Error::other(error_message));
+        }
+
+        // The full path is kept so 'undo' works after changing directories
+        let original: PathBuf = env::current_dir()?.join(file);
+
+        trashed.push((move_to_trash(&original)?, original));
+    }
+    Ok(())
+}
+
+/// Implements a built-in command 'empty-trash' that permanently removes the files 'rm' moved to
+/// the trash
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn empty_trash_builtin(args: &[String]) -> Result<(), Error> {
+    // If any arguments are given
     if args.len() > 1 {
-        return Err(Error::new(ErrorKind::InvalidInput, "Usage: clear"));
+        return Err(Error::new(ErrorKind::InvalidInput, "Usage: empty-trash"));
     }
 
-    // Clearing the users screen
-    print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
+    empty_trash()
+}
 
-    Ok(())
+/// Recursively removes a path, skipping anything on a different filesystem from it, such as
+/// other filesystems mounted inside it
+///
+/// # Arguments
+///
+/// * `path` - The file or directory to remove
+/// * `device` - The device id of the filesystem being removed from
+///
+/// # Return value
+///
+/// True if the path was removed, or false if something in it was skipped
+fn remove_on_device(path: &Path, device: u64) -> Result<bool, Error> {
+    // Symlinks are removed themselves, never followed
+    let path_metadata: Metadata = symlink_metadata(path)?;
+
+    if path_metadata.dev() != device {
+        eprintln!(
+            "rm: skipping '{}', since it's on a different device",
+            path.display()
+        );
+        return Ok(false);
+    }
+
+    if !path_metadata.is_dir() {
+        remove_file(path)?;
+        return Ok(true);
+    }
+
+    // Whether every entry in the directory was removed
+    let mut emptied: bool = true;
+
+    for entry in read_dir(path)? {
+        if !remove_on_device(&entry?.path(), device)? {
+            emptied = false;
+        }
+    }
+
+    // A directory still holding skipped entries cannot be removed
+    if emptied {
+        remove_dir(path)?;
+    }
+    Ok(emptied)
+}
+
+/// Separates the flags of a builtin from its operands.
+///
+/// Any argument starting with '-' is treated as a flag until a "--" argument is found, after which
+/// every argument is an operand.
+///
+/// # Arguments
+///
+/// * `args` - The arguments of a builtin, not including the builtin's name.
+///
+/// # Return value
+///
+/// A tuple of the flags and the operands, in the order they were given.
+fn split_flags(args: &[String]) -> (Vec<&str>, Vec<&str>) {
+    let mut flags: Vec<&str> = Vec::new();
+    let mut operands: Vec<&str> = Vec::new();
+
+    // Whether a "--" end of options marker has been found
+    let mut end_of_options: bool = false;
+
+    for arg in args {
+        if end_of_options {
+            operands.push(arg);
+        } else if arg == "--" {
+            end_of_options = true;
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            flags.push(arg);
+        } else {
+            operands.push(arg);
+        }
+    }
+
+    (flags, operands)
 }
 
-/// Implements a built-in command 'cat'
-fn display_file_contents(args: &[String]) -> Result<(), Error> {
-    // If no arguments or too many arguments are given
-    if args.len() == 1 || args.len() > 2 {
+/// Implements a built-in version of the 'touch' command.
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `undo_log` - How to reverse the destructive builtins that have run
+fn touch_builtin(args: &[String], undo_log: &mut UndoLog) -> Result<(), Error> {
+    // Flags and files given as arguments, ignoring "touch" itself
+    let (flags, files) = split_flags(&args[1..]);
+
+    // If no files are given or any flag is given
+    if files.is_empty() || !flags.is_empty() {
         return Err(Error::new(
             ErrorKind::InvalidInput,
-            "Usage: cat <path to file>",
+            "usage: touch [--] <file1 file2 ...>",
         ));
     }
 
-    // Given file to display to the screen
-    let display_file: File = OpenOptions::new().read(true).open(&args[1])?;
+    // Files that did not exist before, with when they were created, which 'undo' removes
+    let mut created: Vec<(PathBuf, SystemTime)> = Vec::new();
+
+    for file_path in files {
+        // File to be created or have its time updated
+        let file: &Path = Path::new(file_path);
+
+        //if the file already exists we add a new line to the file, and immediately remove it
+        if file.exists() {
+            let file_to_change: File = OpenOptions::new().append(true).open(file)?;
+
+            //get metadata to access for adding and removing new lines
+            let metadata: Metadata = file.metadata()?;
+
+            // adds new line
+            file_to_change.set_len(metadata.len() + 1)?;
 
-    // Buffered reader of given file
-    let reader: BufReader<File> = BufReader::new(display_file);
+            // removes new line
+            file_to_change.set_len(file.metadata()?.len() - 1)?;
+        }
+        //if the file does not exist, create it
+        else {
+            let created_file: File = File::create(file)?;
 
-    // Loop and print all lines of the file
-    for line in reader.lines() {
-        match line {
-            Ok(ok) => println!("{}", ok),
-            Err(e) => return Err(e),
+            // The full path is kept so 'undo' works after changing directories, and the time so
+            // it only removes the file if nothing has changed it since
+            created.push((
+                env::current_dir()?.join(file),
+                created_file.metadata()?.modified()?,
+            ));
         }
     }
+
+    if !created.is_empty() {
+        undo_log.record(UndoAction::RemoveCreated(created));
+    }
     Ok(())
 }
+
+/// Implements a built-in command 'undo' that reverses the most recent destructive builtin
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `undo_log` - How to reverse the destructive builtins that have run
+fn undo_builtin(args: &[String], undo_log: &mut UndoLog) -> Result<(), Error> {
+    // If any arguments are given
+    if args.len() > 1 {
+        return Err(Error::new(ErrorKind::InvalidInput, "Usage: undo"));
+    }
+
+    undo_log.undo()
+}
+
+/// The directory 'cd' last changed from, which 'cd -' changes back to
+static PREVIOUS_DIRECTORY: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Implements a built-in version of the 'cd' command. A directory of `@<name>` is the directory of
+/// the bookmark with that name, `-` is the previous directory, and no directory is the home
+/// directory. With the 'cd_to_file_parent' setting on, a file is taken as the directory it is in.
+/// A path with wildcards changes to the one directory it matches.
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `history` - An object that contains all previously entered commands
+/// * `config` - The shell's settings
+fn change_dir_builtin(
+    args: &[String],
+    history: &mut History,
+    config: &Config,
+) -> Result<(), Error> {
+    // If too many arguments are given
+    if args.len() > 2 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: cd [directory path | @bookmark | -]",
+        ));
+    }
+
+    // Directory to change to
+    let directory: PathBuf = match args.get(1).map(String::as_str) {
+        None => env::var("HOME")
+            .map(PathBuf::from)
+            .map_err(|_| Error::new(ErrorKind::NotFound, "HOME not set"))?,
+        Some("-") => PREVIOUS_DIRECTORY
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "No previous directory"))?,
+        Some(name) if name.starts_with('@') => Bookmarks::load()?.get(&name[1..])?.clone(),
+        Some(pattern) if has_wildcard(pattern) => match_directory(pattern)?,
+        Some(path) => PathBuf::from(unescape_wildcards(path)),
+    };
+
+    // A file stands for the directory holding it, if the setting is on
+    let directory: PathBuf = if directory.is_file() && config.get("cd_to_file_parent") == "true" {
+        parent_directory(&directory)
+    } else {
+        directory
+    };
+
+    // If the given path is a valid directory
+    if directory.is_dir() {
+        let current_directory: PathBuf = env::current_dir()?;
+        set_current_dir(&directory)?;
+        *PREVIOUS_DIRECTORY.lock().unwrap_or_else(|e| e.into_inner()) = Some(current_directory);
+
+        // As in other shells, the directory switched to with 'cd -' is printed
+        if args.get(1).is_some_and(|arg| arg == "-") {
+            println!("{}", env::current_dir()?.display());
+        }
+        // The directory has already changed, so failing to record it for 'z' does not fail 'cd'
+        let recorded: Result<(), Error> =
+            env::current_dir().and_then(|directory| Frecency::load()?.visit(&directory));
+        if let Err(e) = recorded {
+            print_error("Could not record the directory for z", &e, config);
+        }
+        history.change_directory()
+    } else {
+        let error_message: String = directory.display().to_string() + " is not a valid directory";
+        Err(Error::other(error_message))
+    }
+}
+
+/// Finds the one directory a path with wildcards matches
+///
+/// # Arguments
+///
+/// * `pattern` - The path with wildcards
+///
+/// # Return value
+///
+/// The matching directory, or an error listing the candidates if there are several
+fn match_directory(pattern: &str) -> Result<PathBuf, Error> {
+    let directories: Vec<String> = glob_matches(pattern)
+        .into_iter()
+        .filter(|path| Path::new(path).is_dir())
+        .collect();
+
+    match &directories[..] {
+        [directory] => Ok(PathBuf::from(directory)),
+        [] => {
+            let error_message: String = pattern.to_owned() + ": no matching directory";
+            Err(Error::new(ErrorKind::NotFound, error_message))
+        }
+        _ => {
+            let error_message: String = pattern.to_owned()
+                + ": matches more than one directory\n"
+                + &directories.join("  ");
+            Err(Error::new(ErrorKind::InvalidInput, error_message))
+        }
+    }
+}
+
+/// Implements a built-in version of the 'pushd' command, which changes to a directory and saves the
+/// current one on the directory stack. With no directory, it swaps the current directory with the
+/// top of the stack, and with `+N` it rotates the stack so the Nth entry of 'dirs -v' is current.
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `history` - An object that contains all previously entered commands
+/// * `dir_stack` - Directories saved by 'pushd'
+/// * `config` - The shell's settings
+fn push_dir_builtin(
+    args: &[String],
+    history: &mut History,
+    dir_stack: &mut DirStack,
+    config: &Config,
+) -> Result<(), Error> {
+    // If too many arguments are given
+    if args.len() > 2 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: pushd [directory path | +N]",
+        ));
+    }
+
+    let current_directory: PathBuf = env::current_dir()?;
+
+    // Position given as '+N', if any
+    let position: Option<usize> = args.get(1).and_then(|arg| stack_position(arg));
+
+    match (args.get(1), position) {
+        // The current directory is already first, so '+0' changes nothing
+        (_, Some(0)) => {}
+        (_, Some(position)) => {
+            let directory: String = dir_stack.get(position)?.display().to_string();
+            change_dir_builtin(&[String::from("cd"), directory], history, config)?;
+            dir_stack.rotate(position, current_directory)?;
+        }
+        (Some(directory), None) => {
+            change_dir_builtin(&[String::from("cd"), directory.clone()], history, config)?;
+            dir_stack.push(current_directory);
+        }
+        (None, None) => {
+            let top: String = dir_stack.top()?.display().to_string();
+            change_dir_builtin(&[String::from("cd"), top], history, config)?;
+            dir_stack.replace_top(current_directory)?;
+        }
+    }
+    dir_stack.display()
+}
+
+/// Implements a built-in version of the 'popd' command, which changes to the directory on top of
+/// the directory stack and removes it from the stack. With `+N`, the Nth entry of 'dirs -v' is
+/// removed instead, without changing directories.
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `history` - An object that contains all previously entered commands
+/// * `dir_stack` - Directories saved by 'pushd'
+/// * `config` - The shell's settings
+fn pop_dir_builtin(
+    args: &[String],
+    history: &mut History,
+    dir_stack: &mut DirStack,
+    config: &Config,
+) -> Result<(), Error> {
+    // Position of the entry to remove, where the current directory is 0
+    let position: usize = match args {
+        [_] => 0,
+        [_, arg] => stack_position(arg)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Usage: popd [+N]"))?,
+        _ => return Err(Error::new(ErrorKind::InvalidInput, "Usage: popd [+N]")),
+    };
+
+    if position == 0 {
+        // The directory is only removed once it has been changed to
+        let top: String = dir_stack.top()?.display().to_string();
+        change_dir_builtin(&[String::from("cd"), top], history, config)?;
+        dir_stack.pop()?;
+    } else {
+        dir_stack.remove(position)?;
+    }
+
+    dir_stack.display()
+}
+
+/// Parses a `+N` argument of 'pushd' or 'popd'
+///
+/// # Arguments
+///
+/// * `arg` - The argument
+///
+/// # Return value
+///
+/// The position N, or `None` if the argument is not of that form
+fn stack_position(arg: &str) -> Option<usize> {
+    arg.strip_prefix('+')?.parse().ok()
+}
+
+/// Implements a built-in version of the 'dirs' command, which prints the directory stack. `-c`
+/// clears the stack and `-v` prints each entry on its own line with its position.
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `dir_stack` - Directories saved by 'pushd'
+fn dirs_builtin(args: &[String], dir_stack: &mut DirStack) -> Result<(), Error> {
+    match args
+        .iter()
+        .skip(1)
+        .map(String::as_str)
+        .collect::<Vec<&str>>()[..]
+    {
+        [] => dir_stack.display(),
+        ["-v"] => dir_stack.display_numbered(),
+        ["-c"] => {
+            dir_stack.clear();
+            Ok(())
+        }
+        _ => Err(Error::new(ErrorKind::InvalidInput, "Usage: dirs [-c | -v]")),
+    }
+}
+
+/// Implements a built-in command 'z' that changes to the most frequently and recently visited
+/// directory whose path contains each of the given patterns
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `history` - An object that contains all previously entered commands
+/// * `config` - The shell's settings
+fn jump_builtin(args: &[String], history: &mut History, config: &Config) -> Result<(), Error> {
+    // If no patterns are given
+    if args.len() == 1 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: z <pattern> [pattern ...]",
+        ));
+    }
+
+    let directory: PathBuf = Frecency::load()?.best_match(&args[1..])?;
+    change_dir_builtin(
+        &[String::from("cd"), directory.display().to_string()],
+        history,
+        config,
+    )
+}
+
+/// Implements a built-in command 'goto' that changes to the directory of a bookmark
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `history` - An object that contains all previously entered commands
+/// * `config` - The shell's settings
+fn goto_builtin(args: &[String], history: &mut History, config: &Config) -> Result<(), Error> {
+    // If a single bookmark is not given
+    if args.len() != 2 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: goto <bookmark>",
+        ));
+    }
+
+    change_dir_builtin(
+        &[String::from("cd"), "@".to_owned() + &args[1]],
+        history,
+        config,
+    )
+}
+
+/// Implements a built-in command 'bookmark' that saves, lists, and removes named directories
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn bookmark_builtin(args: &[String]) -> Result<(), Error> {
+    let mut bookmarks: Bookmarks = Bookmarks::load()?;
+
+    match args
+        .iter()
+        .skip(1)
+        .map(String::as_str)
+        .collect::<Vec<&str>>()[..]
+    {
+        ["add", name] => bookmarks.add(name),
+        ["rm", name] => bookmarks.remove(name),
+        ["list"] | [] => {
+            bookmarks.display();
+            Ok(())
+        }
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: bookmark add <name>\n       bookmark rm <name>\n       bookmark list",
+        )),
+    }
+}
+
+/// Implements a built-in version of the 'pwd' command.
+fn pwd_builtin() {
+    println!(
+        "{}",
+        env::current_dir()
+            .expect("Error: Could not access current directory env")
+            .display()
+    )
+}
+
+/// Implements a built-in command history
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `history` - An object that contains all previously entered commands
+fn history_builtin(args: &[String], history: &mut History) -> Result<(), Error> {
+    // If no arguments are given
+    if args.len() == 1 {
+        // print all history
+        history.display_full_history();
+    }
+    // If the history should be exported for other programs
+    else if args[1] == "--export" {
+        if args.len() != 3 || args[2] != "json" {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Usage: history --export json",
+            ));
+        }
+        history.export_json();
+    }
+    // If command usage statistics are wanted
+    else if args[1] == "--stats" && args.len() <= 3 {
+        match args.get(2).map(|limit| limit.parse::<usize>()) {
+            None => history.display_stats(None),
+            Some(Ok(limit)) => history.display_stats(Some(limit)),
+            Some(Err(_)) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Non-number argument given",
+                ))
+            }
+        }
+    }
+    // If two arguments are given
+    else if args.len() == 2 {
+        // Check if the received argument is a number
+        match &args[1].parse::<usize>() {
+            Ok(ok) => history.display_num_commands(*ok),
+            Err(_) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Non-number argument given",
+                ))
+            }
+        }
+    } else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: history <num of previous commands>\n       history --stats [num of commands]\n       history --export json",
+        ));
+    }
+    Ok(())
+}
+
+/// Implements a built-in command 'hash' for the cached locations of commands
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `path_cache` - Cached locations of commands on PATH
+fn hash_builtin(args: &[String], path_cache: &mut PathCache) -> Result<(), Error> {
+    // If no arguments are given
+    if args.len() == 1 {
+        path_cache.display();
+    }
+    // If the cache should be cleared
+    else if args.len() == 2 && args[1] == "-r" {
+        path_cache.clear();
+    }
+    // Look up and cache each given command
+    else {
+        for name in &args[1..] {
+            if name.starts_with('-') {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Usage: hash [-r] [command1 command2 ...]",
+                ));
+            }
+
+            if path_cache.lookup(name).is_none() {
+                let error_message: String = name.to_owned() + ": not found";
+                return Err(Error::new(ErrorKind::NotFound, error_message));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Implements a built-in command 'exec' that replaces the shell with the given command, which
+/// keeps the shell's PID. With no command nothing is done.
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `history` - An object that contains all previously entered commands
+/// * `path_cache` - Cached locations of commands on PATH
+/// * `config` - The shell's settings
+///
+/// # Return value
+///
+/// Only returns if the command could not be run
+fn exec_builtin(
+    args: &[String],
+    history: &mut History,
+    path_cache: &mut PathCache,
+    config: &Config,
+) -> Result<(), Error> {
+    if args.len() == 1 {
+        return Ok(());
+    }
+
+    // The shell does not get to keep or forget its history on exit once it is replaced
+    if config.get("clear_history_on_exit") == "true" {
+        history.clear()?;
+    } else {
+        history.save()?;
+    }
+
+    // Only returns on failure
+    Err(new_command(&args[1], path_cache).args(&args[2..]).exec())
+}
+
+/// Implements a built-in command 'exit' that records the status for the shell to exit with, the
+/// status of the last command by default. The shell exits once the builtin returns.
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `previous_status` - The exit status of the command before 'exit'
+fn exit_builtin(args: &[String], previous_status: i32) -> Result<(), Error> {
+    match args.len() {
+        1 => set_last_status(previous_status),
+        2 => match args[1].parse() {
+            Ok(status) => set_last_status(status),
+            Err(_) => {
+                let error_message: String = args[1].to_owned() + ": numeric argument required";
+                return Err(Error::new(ErrorKind::InvalidInput, error_message));
+            }
+        },
+        _ => return Err(Error::new(ErrorKind::InvalidInput, "Usage: exit [status]")),
+    }
+    Ok(())
+}
+
+/// The lowest and highest niceness a process can have
+const NICENESS_RANGE: (i32, i32) = (-20, 19);
+
+/// Implements a built-in command 'nice' that runs a command with its niceness raised by the given
+/// adjustment, 10 by default, which lowers its scheduling priority. Only root can give a negative
+/// adjustment. With no command, the shell's niceness is printed.
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `options` - The shell's options
+/// * `path_cache` - Cached locations of commands on PATH
+/// * `config` - The shell's settings
+fn nice_builtin(
+    args: &[String],
+    options: &ShellOptions,
+    path_cache: &mut PathCache,
+    config: &Config,
+) -> Result<(), Error> {
+    let usage = || {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: nice [-n <adjustment>] [command [args]]",
+        )
+    };
+
+    // Commands run from the shell start with its niceness
+    let current: i32 = niceness()?;
+
+    if args.len() == 1 {
+        println!("{}", current);
+        return Ok(());
+    }
+
+    // The adjustment and the command it applies to
+    let (adjustment, command): (i32, &[String]) = if args[1] == "-n" {
+        let adjustment: i32 = args
+            .get(2)
+            .and_then(|adjustment| adjustment.parse().ok())
+            .ok_or_else(usage)?;
+        (adjustment, &args[3..])
+    } else {
+        (10, &args[1..])
+    };
+
+    if command.is_empty() || command[0].starts_with('-') {
+        return Err(usage());
+    }
+
+    // Checked here so the error is clearer than the one setpriority would give
+    // Safety: geteuid has no preconditions and cannot fail
+    if adjustment < 0 && unsafe { libc::geteuid() } != 0 {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "Only root can give a negative adjustment",
+        ));
+    }
+
+    // Niceness beyond the range is set to its nearest end, as setpriority does
+    let niceness: i32 = current
+        .saturating_add(adjustment)
+        .clamp(NICENESS_RANGE.0, NICENESS_RANGE.1);
+
+    let mut process: Command = new_command(&command[0], path_cache);
+    process.args(&command[1..]);
+
+    // Only the command's niceness is changed, in the child before it runs
+    // Safety: the closure runs between fork and exec, where it only calls setpriority, which is
+    // async-signal-safe, and reads errno without allocating
+    unsafe {
+        process.pre_exec(move || {
+            if libc::setpriority(libc::PRIO_PROCESS, 0, niceness) == -1 {
+                return Err(Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+
+    // 'nice' exits with the command's status
+    let status: i32 = execute(
+        &mut process,
+        Vec::new(),
+        options,
+        false,
+        config.get("set_title") == "true",
+    )?;
+    set_last_status(status);
+    Ok(())
+}
+
+/// Gets the niceness of the shell
+fn niceness() -> Result<i32, Error> {
+    // Safety: getpriority has no preconditions and only reads the shell's own priority
+    let niceness: i32 = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+
+    // -1 is also a valid niceness, so it is only a failure if errno holds an error getpriority
+    // reports
+    if niceness != -1 {
+        return Ok(niceness);
+    }
+    let error: Error = Error::last_os_error();
+    match error.raw_os_error() {
+        Some(libc::ESRCH) | Some(libc::EINVAL) => Err(error),
+        _ => Ok(niceness),
+    }
+}
+
+/// Implements a built-in command 'set' for turning shell options on and off
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `options` - The shell's options
+fn set_builtin(args: &[String], options: &mut ShellOptions) -> Result<(), Error> {
+    // '-o' turns an option on and '+o' turns it off. Without a name they list the options
+    match (args.len(), args.get(1).map(String::as_str)) {
+        (1, _) | (2, Some("-o")) => {
+            options.display();
+            Ok(())
+        }
+        (2, Some("+o")) => {
+            options.display_commands();
+            Ok(())
+        }
+        (3, Some("-o")) => options.set(&args[2], true),
+        (3, Some("+o")) => options.set(&args[2], false),
+        _ => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: set [-o|+o] [option name]",
+        )),
+    }
+}
+
+/// Implements a built-in command 'trap' for running commands when signals are received
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `traps` - Commands registered to run on signals
+fn trap_builtin(args: &[String], traps: &mut Traps) -> Result<(), Error> {
+    // If no arguments are given
+    if args.len() == 1 {
+        traps.display();
+    }
+    // List the signals that can be trapped
+    else if args.len() == 2 && args[1] == "-l" {
+        for (number, name) in TRAPPABLE_SIGNALS.iter() {
+            println!("{:>2}) {}", number, name);
+        }
+    }
+    // '-' removes the command for each given signal
+    else if args.len() > 2 && args[1] == "-" {
+        for signal in &args[2..] {
+            traps.remove(signal)?;
+        }
+    }
+    // Register the command for each given signal
+    else if args.len() > 2 {
+        for signal in &args[2..] {
+            traps.set(signal, &args[1])?;
+        }
+    } else {
+        let signals: Vec<&str> = TRAPPABLE_SIGNALS.iter().map(|(_, name)| *name).collect();
+        let error_message: String = format!(
+            "Usage: trap [-l] [<command>|-] <signal1 signal2 ...>\nSignals that can be trapped: {}",
+            signals.join(", ")
+        );
+        return Err(Error::new(ErrorKind::InvalidInput, error_message));
+    }
+    Ok(())
+}
+
+/// Implements a built-in command 'clear'
+fn clear_builtin(args: &[String]) -> Result<(), Error> {
+    // If too many arguments are given
+    if args.len() > 1 {
+        return Err(Error::new(ErrorKind::InvalidInput, "Usage: clear"));
+    }
+
+    // Clearing the users screen
+    print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
+
+    Ok(())
+}
+
+/// Implements a built-in command 'cat', which prints each of the given files in turn. `-n`
+/// numbers the lines, counting on across files. A file that cannot be read is reported and the
+/// rest are still printed.
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+/// * `config` - The shell's settings, used for errors
+fn display_file_contents(args: &[String], config: &Config) -> Result<(), Error> {
+    // Flags and files given as arguments
+    let (flags, files) = split_flags(&args[1..]);
+
+    let number_lines: bool = flags.contains(&"-n");
+    let progress: bool = flags.contains(&"--progress");
+
+    // If no files, an unknown flag, or both flags are given
+    if files.is_empty()
+        || flags
+            .iter()
+            .any(|flag| *flag != "-n" && *flag != "--progress")
+        || (number_lines && progress)
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: cat [-n | --progress] <path to file> [path to file ...]",
+        ));
+    }
+
+    // Number of the next line, when numbering
+    let mut line_number: usize = 1;
+
+    // Number of files that could not be displayed
+    let mut failures: usize = 0;
+
+    for file in files {
+        let result: Result<(), Error> =
+            OpenOptions::new()
+                .read(true)
+                .open(file)
+                .and_then(|display_file| {
+                    if number_lines {
+                        display_numbered_lines(display_file, &mut line_number)
+                    } else if progress {
+                        display_file_with_progress(display_file)
+                    } else {
+                        // Copy the whole file to stdout in bulk, through a buffered writer so stdout
+                        // is locked once rather than once per line
+                        let mut reader: BufReader<File> = BufReader::new(display_file);
+                        let mut writer: BufWriter<StdoutLock> = BufWriter::new(stdout().lock());
+                        copy(&mut reader, &mut writer)?;
+                        writer.flush()
+                    }
+                });
+
+        if let Err(e) = result {
+            print_error(&("Could not display ".to_owned() + file), &e, config);
+            failures += 1;
+        }
+    }
+
+    if failures > 0 {
+        let error_message: String = format!("{} of the files could not be displayed", failures);
+        return Err(Error::other(error_message));
+    }
+    Ok(())
+}
+
+/// Copies a file to stdout with each line prefixed by its number, right aligned
+///
+/// # Arguments
+///
+/// * `display_file` - The file to display to the screen
+/// * `line_number` - The number of the file's first line, which is left as the number after its
+///   last line
+fn display_numbered_lines(display_file: File, line_number: &mut usize) -> Result<(), Error> {
+    let mut reader: BufReader<File> = BufReader::new(display_file);
+    let mut writer: BufWriter<StdoutLock> = BufWriter::new(stdout().lock());
+
+    // Lines are read as bytes so files that are not UTF-8 are printed as they are
+    let mut line: Vec<u8> = Vec::new();
+    while reader.read_until(b'\n', &mut line)? > 0 {
+        write!(writer, "{:>6}\t", line_number)?;
+        writer.write_all(&line)?;
+        *line_number += 1;
+        line.clear();
+    }
+    writer.flush()
+}
+
+/// Copies a file to stdout while showing the bytes read and throughput on stderr
+///
+/// # Arguments
+///
+/// * `display_file` - The file to display to the screen
+fn display_file_with_progress(display_file: File) -> Result<(), Error> {
+    // How often the progress line is redrawn
+    const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+    // Total size of the file used to calculate the percentage read
+    let total_bytes: u64 = display_file.metadata()?.len();
+
+    let mut reader: BufReader<File> = BufReader::new(display_file);
+    let mut buffer: [u8; 65536] = [0; 65536];
+    let mut bytes_read: u64 = 0;
+
+    let start: Instant = Instant::now();
+    let mut last_update: Instant = start;
+
+    let mut stdout = stdout().lock();
+
+    loop {
+        let count: usize = reader.read(&mut buffer)?;
+
+        // End of file reached
+        if count == 0 {
+            break;
+        }
+
+        stdout.write_all(&buffer[..count])?;
+        bytes_read += count as u64;
+
+        if last_update.elapsed() >= PROGRESS_INTERVAL {
+            print_progress(bytes_read, total_bytes, start.elapsed());
+            last_update = Instant::now();
+        }
+    }
+    stdout.flush()?;
+
+    // Always finish with the final totals on their own line
+    print_progress(bytes_read, total_bytes, start.elapsed());
+    eprintln!();
+
+    Ok(())
+}
+
+/// Redraws the progress line on stderr
+///
+/// # Arguments
+///
+/// * `bytes_read` - Number of bytes read so far
+/// * `total_bytes` - Size of the file being read
+/// * `elapsed` - Time since reading started
+fn print_progress(bytes_read: u64, total_bytes: u64, elapsed: Duration) {
+    // An empty file is always complete
+    let percent: u64 = (bytes_read * 100).checked_div(total_bytes).unwrap_or(100);
+
+    // Bytes per second, avoiding a division by zero for very fast reads
+    let seconds: f64 = elapsed.as_secs_f64();
+    let throughput: f64 = if seconds > 0.0 {
+        bytes_read as f64 / seconds
+    } else {
+        bytes_read as f64
+    };
+
+    eprint!(
+        "\r{} / {} bytes ({}%) {:.1} KiB/s",
+        bytes_read,
+        total_bytes,
+        percent,
+        throughput / 1024.0
+    );
+}
+
+/// The most pairs of lines 'diff' compares, which keeps its table of common lines to tens of
+/// megabytes
+const DIFF_MAX_LINE_PAIRS: usize = 4_000_000;
+
+/// Implements a built-in command 'diff' that compares two files line by line. The exit status is 1
+/// if the files differ.
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn diff_builtin(args: &[String]) -> Result<(), Error> {
+    // Flags and files given as arguments
+    let (flags, files) = split_flags(&args[1..]);
+
+    // If not exactly two files or an unknown flag are given
+    if files.len() != 2 || flags.iter().any(|flag| *flag != "-q") {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: diff [-q] <file1> <file2>",
+        ));
+    }
+
+    let old_lines: Vec<String> = read_lines(files[0])?;
+    let new_lines: Vec<String> = read_lines(files[1])?;
+
+    // Identical files produce no output
+    if old_lines == new_lines {
+        return Ok(());
+    }
+    set_last_status(1);
+
+    // Only report that the files differ
+    if flags.contains(&"-q") {
+        println!("Files {} and {} differ", files[0], files[1]);
+        return Ok(());
+    }
+
+    // The table of common lines grows with the product of the files' lengths
+    if old_lines.len().saturating_mul(new_lines.len()) > DIFF_MAX_LINE_PAIRS {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The files are too long to show the lines that differ; use -q",
+        ));
+    }
+
+    println!("--- {}", files[0]);
+    println!("+++ {}", files[1]);
+    for line in diff_lines(&old_lines, &new_lines) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Reads every line of a file
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to read
+fn read_lines(path: &str) -> Result<Vec<String>, Error> {
+    BufReader::new(File::open(path)?).lines().collect()
+}
+
+/// Reads every line of a file, or of stdin when no file is given
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to read, or `None` (or "-") for stdin
+fn read_input_lines(path: Option<&str>) -> Result<Vec<String>, Error> {
+    match path {
+        Some(path) if path != "-" => read_lines(path),
+        _ => stdin().lock().lines().collect(),
+    }
+}
+
+/// Builds a line diff of two files from their longest common subsequence of lines
+///
+/// # Arguments
+///
+/// * `old_lines` - The lines of the original file
+/// * `new_lines` - The lines of the changed file
+///
+/// # Return value
+///
+/// Every line of both files in order, prefixed with '-' if it was removed, '+' if it was added,
+/// or a space if it is in both
+fn diff_lines(old_lines: &[String], new_lines: &[String]) -> Vec<String> {
+    // common[i][j] is the length of the longest common subsequence of old_lines[i..] and
+    // new_lines[j..]
+    let mut common: Vec<Vec<usize>> = vec![vec![0; new_lines.len() + 1]; old_lines.len() + 1];
+
+    for i in (0..old_lines.len()).rev() {
+        for j in (0..new_lines.len()).rev() {
+            common[i][j] = if old_lines[i] == new_lines[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff: Vec<String> = Vec::new();
+    let mut i: usize = 0;
+    let mut j: usize = 0;
+
+    // Walk both files, keeping lines that are part of the common subsequence
+    while i < old_lines.len() && j < new_lines.len() {
+        if old_lines[i] == new_lines[j] {
+            diff.push(" ".to_owned() + &old_lines[i]);
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            diff.push("-".to_owned() + &old_lines[i]);
+            i += 1;
+        } else {
+            diff.push("+".to_owned() + &new_lines[j]);
+            j += 1;
+        }
+    }
+
+    // Whatever is left in either file was removed or added
+    for line in &old_lines[i..] {
+        diff.push("-".to_owned() + line);
+    }
+    for line in &new_lines[j..] {
+        diff.push("+".to_owned() + line);
+    }
+
+    diff
+}
+
+/// Implements a built-in pager 'more'/'less' that shows a file or stdin a screen at a time
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn pager_builtin(args: &[String]) -> Result<(), Error> {
+    // If too many arguments are given
+    if args.len() > 2 {
+        let error_message: String = "Usage: ".to_owned() + &args[0] + " [path to file]";
+        return Err(Error::new(ErrorKind::InvalidInput, error_message));
+    }
+
+    // Lines of the given file, or of stdin if no file is given
+    let lines: Vec<String> = read_input_lines(args.get(1).map(String::as_str))?;
+
+    // Without a terminal to page on, show everything at once
+    let height: usize = match terminal_height() {
+        Some(height) if stdout().is_terminal() => height,
+        _ => {
+            for line in &lines {
+                println!("{}", line);
+            }
+            return Ok(());
+        }
+    };
+
+    // Key presses come from the terminal even when the text came from stdin
+    let mut keys: File = File::open("/dev/tty")?;
+    let _raw_mode: RawMode = RawMode::enable(keys.as_raw_fd())?;
+
+    // One row is kept for the status line
+    page_lines(
+        &lines,
+        height.saturating_sub(1).max(1),
+        &mut keys,
+        &mut stdout(),
+    )
+}
+
+/// Writes lines a page at a time, waiting for a key press between pages
+///
+/// # Arguments
+///
+/// * `lines` - The lines to show
+/// * `page_height` - The number of lines in a page
+/// * `keys` - Where key presses are read from. Space shows the next page, Enter shows the next
+///   line and 'q' quits
+/// * `output` - Where the lines are written
+fn page_lines<R: Read, W: Write>(
+    lines: &[String],
+    page_height: usize,
+    keys: &mut R,
+    output: &mut W,
+) -> Result<(), Error> {
+    // Index of the next line to show
+    let mut shown: usize = 0;
+
+    // Number of lines to show before the next key press
+    let mut to_show: usize = page_height;
+
+    while shown < lines.len() {
+        for line in lines.iter().skip(shown).take(to_show) {
+            writeln!(output, "{}", line)?;
+        }
+        shown = lines.len().min(shown + to_show);
+
+        if shown == lines.len() {
+            break;
+        }
+
+        write!(
+            output,
+            "\x1b[7m--More--({}%)\x1b[0m",
+            shown * 100 / lines.len()
+        )?;
+        output.flush()?;
+
+        let mut key: [u8; 1] = [0];
+        let read: usize = keys.read(&mut key)?;
+
+        // Erase the status line
+        write!(output, "\r\x1b[K")?;
+
+        match (read, key[0]) {
+            (0, _) | (_, b'q') => break,
+            (_, b' ') => to_show = page_height,
+            (_, b'\n') | (_, b'\r') => to_show = 1,
+            _ => to_show = 0,
+        }
+    }
+    output.flush()
+}
+
+/// Implements a built-in command 'date' that prints the current time
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn date_builtin(args: &[String]) -> Result<(), Error> {
+    const USAGE: &str = "Usage: date [-u] [+format]";
+
+    // Whether to print the time in UTC instead of local time
+    let mut utc: bool = false;
+
+    // strftime style format of the printed time
+    let mut format: &str = "%a %b %e %H:%M:%S %Z %Y";
+
+    for arg in &args[1..] {
+        if arg == "-u" {
+            utc = true;
+        } else if let Some(custom_format) = arg.strip_prefix('+') {
+            format = custom_format;
+        } else {
+            return Err(Error::new(ErrorKind::InvalidInput, USAGE));
+        }
+    }
+
+    // Formatting with an invalid specifier would panic, so reject it first
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        let error_message: String = "Invalid format ".to_owned() + format + "\n" + USAGE;
+        return Err(Error::new(ErrorKind::InvalidInput, error_message));
+    }
+
+    if utc {
+        println!("{}", Utc::now().format(format));
+    } else {
+        println!("{}", Local::now().format(format));
+    }
+    Ok(())
+}
+
+/// Implements a built-in command 'whoami' that prints the current user's name
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn whoami_builtin(args: &[String]) -> Result<(), Error> {
+    // If any arguments are given
+    if args.len() > 1 {
+        return Err(Error::new(ErrorKind::InvalidInput, "Usage: whoami"));
+    }
+
+    match current_user_name() {
+        Some(user) => println!("{}", user),
+        None => {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                "No name for the current user",
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Implements a built-in command 'hostname' that prints the system's host name
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn hostname_builtin(args: &[String]) -> Result<(), Error> {
+    // If any arguments are given
+    if args.len() > 1 {
+        return Err(Error::new(ErrorKind::InvalidInput, "Usage: hostname"));
+    }
+
+    println!("{}", host_name()?);
+    Ok(())
+}
+
+/// Implements a built-in version of the 'echo' command. `-n` leaves off the trailing newline and
+/// `-e` interprets `\n`, `\t`, and `\\`.
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn echo_builtin(args: &[String]) -> Result<(), Error> {
+    let mut newline: bool = true;
+    let mut escapes: bool = false;
+
+    // Leading arguments made only of option letters are options, as in other shells
+    let mut words: &[String] = &args[1..];
+    while let Some(option) = words.first().and_then(|word| word.strip_prefix('-')) {
+        if option.is_empty() || !option.chars().all(|c| c == 'n' || c == 'e') {
+            break;
+        }
+        newline &= !option.contains('n');
+        escapes |= option.contains('e');
+        words = &words[1..];
+    }
+
+    let mut text: String = words.join(" ");
+    if escapes {
+        text = interpret_escapes(&text);
+    }
+    if newline {
+        text.push('\n');
+    }
+
+    let mut stdout = stdout();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()
+}
+
+/// Replaces the `\n`, `\t`, and `\\` escape sequences in text with the characters they stand for.
+/// Any other backslash is kept.
+///
+/// # Arguments
+///
+/// * `text` - The text to interpret
+fn interpret_escapes(text: &str) -> String {
+    let mut interpreted: String = String::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let escaped: Option<char> = match (c, chars.peek()) {
+            ('\\', Some('n')) => Some('\n'),
+            ('\\', Some('t')) => Some('\t'),
+            ('\\', Some('\\')) => Some('\\'),
+            _ => None,
+        };
+
+        match escaped {
+            Some(escaped) => {
+                chars.next();
+                interpreted.push(escaped);
+            }
+            None => interpreted.push(c),
+        }
+    }
+    interpreted
+}
+
+/// Implements a built-in command 'rev' that prints each line with its characters reversed
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn rev_builtin(args: &[String]) -> Result<(), Error> {
+    // Files to reverse, or stdin if none are given
+    let files: Vec<Option<&str>> = if args.len() == 1 {
+        vec![None]
+    } else {
+        args[1..].iter().map(|file| Some(file.as_str())).collect()
+    };
+
+    for file in files {
+        for line in read_input_lines(file)? {
+            // Reversing chars keeps multi-byte characters intact
+            println!("{}", line.chars().rev().collect::<String>());
+        }
+    }
+    Ok(())
+}
+
+/// Implements a built-in command 'tac' that prints the lines of a file last to first
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn tac_builtin(args: &[String]) -> Result<(), Error> {
+    // If too many arguments are given
+    if args.len() > 2 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: tac [path to file]",
+        ));
+    }
+
+    // A last line without a trailing newline is still its own line, so it is printed first and
+    // not joined onto the line before it
+    let lines: Vec<String> = read_input_lines(args.get(1).map(String::as_str))?;
+
+    for line in lines.iter().rev() {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Implements a built-in command 'nl' that numbers the lines of a file
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn number_lines_builtin(args: &[String]) -> Result<(), Error> {
+    let usage = || {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: nl [-b a|t] [-s <separator>] [path to file]",
+        )
+    };
+
+    // Whether blank lines are numbered too
+    let mut number_blank: bool = false;
+
+    // Text between a line's number and its contents
+    let mut separator: &str = "\t";
+
+    // File to number, or stdin if none is given
+    let mut file: Option<&str> = None;
+
+    let mut remaining = args[1..].iter();
+    while let Some(arg) = remaining.next() {
+        match arg.as_str() {
+            "-b" => match remaining.next().map(String::as_str) {
+                Some("a") => number_blank = true,
+                Some("t") => number_blank = false,
+                _ => return Err(usage()),
+            },
+            "-s" => separator = remaining.next().ok_or_else(usage)?,
+            _ if file.is_none() => file = Some(arg),
+            _ => return Err(usage()),
+        }
+    }
+
+    // Number of the next numbered line
+    let mut number: usize = 1;
+
+    for line in read_input_lines(file)? {
+        if line.is_empty() && !number_blank {
+            println!();
+        } else {
+            println!("{:>6}{}{}", number, separator, line);
+            number += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Implements a built-in command 'column' that aligns the fields of each line into a table
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn column_builtin(args: &[String]) -> Result<(), Error> {
+    let usage = || {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: column [-t] [-s <delimiters>] [path to file]",
+        )
+    };
+
+    // Characters that separate fields, or any whitespace if none are given
+    let mut delimiters: Option<&str> = None;
+
+    // File to format, or stdin if none is given
+    let mut file: Option<&str> = None;
+
+    let mut remaining = args[1..].iter();
+    while let Some(arg) = remaining.next() {
+        match arg.as_str() {
+            // A table is the only output format, so -t is accepted but changes nothing
+            "-t" => {}
+            "-s" => delimiters = Some(remaining.next().ok_or_else(usage)?),
+            _ if file.is_none() => file = Some(arg),
+            _ => return Err(usage()),
+        }
+    }
+
+    // Fields of every non-empty line
+    let rows: Vec<Vec<String>> = read_input_lines(file)?
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| match delimiters {
+            Some(delimiters) => line
+                .split(|c: char| delimiters.contains(c))
+                .map(str::to_string)
+                .collect(),
+            None => line.split_whitespace().map(str::to_string).collect(),
+        })
+        .collect();
+
+    // Width of each column is the width of its longest cell
+    let mut widths: Vec<usize> = Vec::new();
+    for row in &rows {
+        for (index, cell) in row.iter().enumerate() {
+            let width: usize = cell.chars().count();
+            match widths.get_mut(index) {
+                Some(column_width) => *column_width = (*column_width).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+
+    for row in &rows {
+        let mut line: String = String::new();
+
+        for (index, cell) in row.iter().enumerate() {
+            // The last cell of a row is not padded
+            if index + 1 == row.len() {
+                line += cell;
+            } else {
+                line += &format!("{:width$}  ", cell, width = widths[index]);
+            }
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// Implements a built-in command 'strings' that prints the runs of printable characters in a file
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn strings_builtin(args: &[String]) -> Result<(), Error> {
+    let usage = || {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: strings [-n <min length>] [path to file]",
+        )
+    };
+
+    // Shortest run of printable characters that is printed
+    let mut min_length: usize = 4;
+
+    // File to search, or stdin if none is given
+    let mut file: Option<&str> = None;
+
+    let mut remaining = args[1..].iter();
+    while let Some(arg) = remaining.next() {
+        match arg.as_str() {
+            "-n" => {
+                min_length = remaining
+                    .next()
+                    .and_then(|length| length.parse::<usize>().ok())
+                    .filter(|length| *length > 0)
+                    .ok_or_else(usage)?
+            }
+            _ if file.is_none() => file = Some(arg),
+            _ => return Err(usage()),
+        }
+    }
+
+    let input: Box<dyn Read> = match file {
+        None | Some("-") => Box::new(stdin()),
+        Some(path) => Box::new(File::open(path)?),
+    };
+
+    let mut output: BufWriter<StdoutLock> = BufWriter::new(stdout().lock());
+
+    // Printable characters found since the last unprintable byte
+    let mut run: Vec<u8> = Vec::new();
+
+    for byte in BufReader::new(input).bytes() {
+        let byte: u8 = byte?;
+
+        // Printable ASCII, and tabs as they are common in text
+        if byte == b'\t' || (b' '..=b'~').contains(&byte) {
+            run.push(byte);
+            continue;
+        }
+
+        if run.len() >= min_length {
+            output.write_all(&run)?;
+            output.write_all(b"\n")?;
+        }
+        run.clear();
+    }
+
+    // A run may end at the end of the file
+    if run.len() >= min_length {
+        output.write_all(&run)?;
+        output.write_all(b"\n")?;
+    }
+    output.flush()
+}
+
+/// Bytes that files of a known type start with, and the name of the type
+const MAGIC_NUMBERS: [(&[u8], &str); 10] = [
+    (b"\x7fELF", "ELF executable"),
+    (b"\x89PNG\r\n\x1a\n", "PNG image data"),
+    (b"\xff\xd8\xff", "JPEG image data"),
+    (b"GIF87a", "GIF image data"),
+    (b"GIF89a", "GIF image data"),
+    (b"%PDF-", "PDF document"),
+    (b"\x1f\x8b", "gzip compressed data"),
+    (b"BZh", "bzip2 compressed data"),
+    (b"PK\x03\x04", "Zip archive data"),
+    (b"#!", "script text executable"),
+];
+
+/// Implements a built-in command 'file' that guesses the type of each given file
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn file_type_builtin(args: &[String]) -> Result<(), Error> {
+    // If no files are given
+    if args.len() == 1 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: file <file1 file2 ...>",
+        ));
+    }
+
+    for path in &args[1..] {
+        println!("{}: {}", path, file_type(path)?);
+    }
+    Ok(())
+}
+
+/// Guesses the type of a file from its first bytes
+///
+/// # Arguments
+///
+/// * `path` - The path of the file
+fn file_type(path: &str) -> Result<String, Error> {
+    // Symlinks are described rather than followed
+    let file_metadata: Metadata = symlink_metadata(path)?;
+
+    if file_metadata.is_symlink() {
+        return Ok("symbolic link to ".to_owned() + &read_link(path)?.display().to_string());
+    }
+    if file_metadata.is_dir() {
+        return Ok(String::from("directory"));
+    }
+
+    // The start of the file is enough to tell text from data
+    let mut contents: Vec<u8> = Vec::new();
+    File::open(path)?.take(8192).read_to_end(&mut contents)?;
+
+    if contents.is_empty() {
+        return Ok(String::from("empty"));
+    }
+
+    if let Some((_, name)) = MAGIC_NUMBERS
+        .iter()
+        .find(|(magic, _)| contents.starts_with(magic))
+    {
+        return Ok(name.to_string());
+    }
+
+    // Text is printable characters and whitespace only
+    let is_text = |text: &str| {
+        text.chars()
+            .all(|c| !c.is_control() || c.is_ascii_whitespace() || c == '\x1b')
+    };
+
+    // A multi-byte character may be cut off where reading stopped
+    let text: Result<&str, _> = match std::str::from_utf8(&contents) {
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&contents[..e.valid_up_to()]),
+        text => text,
+    };
+
+    match text {
+        Ok(text) if text.is_ascii() && is_text(text) => Ok(String::from("ASCII text")),
+        Ok(text) if is_text(text) => Ok(String::from("UTF-8 Unicode text")),
+        _ => Ok(String::from("data")),
+    }
+}
+
+/// Implements the built-in commands 'md5sum' and 'sha256sum' that print or check the checksums
+/// of files
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn checksum_builtin(args: &[String]) -> Result<(), Error> {
+    let algorithm: Algorithm = if args[0] == "md5sum" {
+        Algorithm::Md5
+    } else {
+        Algorithm::Sha256
+    };
+
+    // If a file of checksums should be checked
+    if args.len() > 1 && args[1] == "-c" {
+        if args.len() != 3 {
+            let error_message: String = "Usage: ".to_owned() + &args[0] + " -c <checksum file>";
+            return Err(Error::new(ErrorKind::InvalidInput, error_message));
+        }
+        return check_checksums(algorithm, &args[2]);
+    }
+
+    // Files to hash, or stdin if none are given
+    let files: Vec<&str> = if args.len() == 1 {
+        vec!["-"]
+    } else {
+        args[1..].iter().map(String::as_str).collect()
+    };
+
+    for file in files {
+        let hash: String = if file == "-" {
+            checksum(algorithm, stdin().lock())?
+        } else {
+            checksum(algorithm, File::open(file)?)?
+        };
+        println!("{}  {}", hash, file);
+    }
+    Ok(())
+}
+
+/// Checks files against a list of checksums, with one '<checksum>  <file>' line per file
+///
+/// # Arguments
+///
+/// * `algorithm` - The hash function the checksums were computed with
+/// * `check_file` - The path of the list of checksums
+fn check_checksums(algorithm: Algorithm, check_file: &str) -> Result<(), Error> {
+    // Number of files that are missing or do not match their checksum
+    let mut failures: usize = 0;
+
+    for line in read_lines(check_file)? {
+        // Lines without a checksum are skipped
+        let (expected, file) = match line.split_once(' ') {
+            Some((expected, file)) => (expected, file.trim_start_matches([' ', '*'])),
+            None => continue,
+        };
+
+        // A file that cannot be read fails the check rather than stopping it
+        match File::open(file).and_then(|contents| checksum(algorithm, contents)) {
+            Ok(hash) if hash.eq_ignore_ascii_case(expected) => println!("{}: OK", file),
+            _ => {
+                println!("{}: FAILED", file);
+                failures += 1;
+            }
+        }
+    }
+
+    if failures > 0 {
+        let error_message: String = failures.to_string() + " file(s) did not match their checksum";
+        return Err(Error::other(error_message));
+    }
+    Ok(())
+}
+
+/// How the 'split' builtin divides its input
+enum SplitSize {
+    // A number of lines per piece
+    Lines(usize),
+    // A number of bytes per piece
+    Bytes(usize),
+}
+
+/// Implements a built-in command 'split' that divides a file into pieces named `<prefix>aa`,
+/// `<prefix>ab`, and so on
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn split_builtin(args: &[String]) -> Result<(), Error> {
+    let usage = || {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: split [-l <lines> | -b <bytes>[K|M]] [path to file [prefix]]",
+        )
+    };
+
+    // Size of each piece, 1000 lines if not given
+    let mut size: SplitSize = SplitSize::Lines(1000);
+
+    // File to split, or stdin if none is given, and the start of each piece's name
+    let mut operands: Vec<&str> = Vec::new();
+
+    let mut remaining = args[1..].iter();
+    while let Some(arg) = remaining.next() {
+        match arg.as_str() {
+            "-l" => {
+                let lines: &str = remaining.next().ok_or_else(usage)?;
+                size = SplitSize::Lines(parse_split_size(lines, false).ok_or_else(usage)?);
+            }
+            "-b" => {
+                let bytes: &str = remaining.next().ok_or_else(usage)?;
+                size = SplitSize::Bytes(parse_split_size(bytes, true).ok_or_else(usage)?);
+            }
+            _ if operands.len() < 2 => operands.push(arg),
+            _ => return Err(usage()),
+        }
+    }
+
+    let input: Box<dyn Read> = match operands.first() {
+        None | Some(&"-") => Box::new(stdin()),
+        Some(path) => Box::new(File::open(path)?),
+    };
+    let mut input: BufReader<Box<dyn Read>> = BufReader::new(input);
+
+    let prefix: &str = operands.get(1).copied().unwrap_or("x");
+
+    // Number of pieces written so far
+    let mut pieces: usize = 0;
+
+    // Reads are at most one piece long, so only a piece's worth of input is held at a time
+    let mut buffer: Vec<u8> = Vec::new();
+    loop {
+        buffer.clear();
+
+        match size {
+            SplitSize::Lines(lines) => {
+                for _ in 0..lines {
+                    if input.read_until(b'\n', &mut buffer)? == 0 {
+                        break;
+                    }
+                }
+            }
+            SplitSize::Bytes(bytes) => {
+                (&mut input).take(bytes as u64).read_to_end(&mut buffer)?;
+            }
+        }
+
+        if buffer.is_empty() {
+            break;
+        }
+
+        File::create(prefix.to_owned() + &split_suffix(pieces)?)?.write_all(&buffer)?;
+        pieces += 1;
+    }
+    Ok(())
+}
+
+/// Parses the size of a piece for 'split'
+///
+/// # Arguments
+///
+/// * `size` - The size as given
+/// * `allow_units` - Whether a K (1024) or M (1024 * 1024) multiplier may end the size
+///
+/// # Return value
+///
+/// The size, or `None` if it is not a positive number
+fn parse_split_size(size: &str, allow_units: bool) -> Option<usize> {
+    let (number, multiplier): (&str, usize) = match size.strip_suffix(['K', 'k']) {
+        Some(number) if allow_units => (number, 1024),
+        _ => match size.strip_suffix('M') {
+            Some(number) if allow_units => (number, 1024 * 1024),
+            _ => (size, 1),
+        },
+    };
+
+    number
+        .parse::<usize>()
+        .ok()
+        .filter(|number| *number > 0)
+        .and_then(|number| number.checked_mul(multiplier))
+}
+
+/// Gets the two letter suffix of a piece written by 'split', from "aa" to "zz"
+///
+/// # Arguments
+///
+/// * `index` - The number of pieces written before this one
+fn split_suffix(index: usize) -> Result<String, Error> {
+    if index >= 26 * 26 {
+        return Err(Error::other("Output file suffixes exhausted"));
+    }
+
+    let first: char = (b'a' + (index / 26) as u8) as char;
+    let second: char = (b'a' + (index % 26) as u8) as char;
+    Ok(format!("{}{}", first, second))
+}
+
+/// Implements a built-in command 'paste' that joins the corresponding lines of files
+///
+/// # Arguments
+///
+/// * `args` - A vector of strings corresponding to the command and its arguments.
+fn paste_builtin(args: &[String]) -> Result<(), Error> {
+    let usage = || {
+        Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: paste [-d <delimiters>] <file1 file2 ...>",
+        )
+    };
+
+    // Characters put between fields, used in turn, or a tab if none are given
+    let mut delimiters: Vec<char> = vec!['\t'];
+
+    // Files to merge, where "-" is stdin
+    let mut files: Vec<&str> = Vec::new();
+
+    let mut remaining = args[1..].iter();
+    while let Some(arg) = remaining.next() {
+        match arg.as_str() {
+            "-d" => {
+                delimiters = remaining.next().ok_or_else(usage)?.chars().collect();
+                if delimiters.is_empty() {
+                    return Err(usage());
+                }
+            }
+            _ => files.push(arg),
+        }
+    }
+
+    if files.is_empty() {
+        return Err(usage());
+    }
+
+    let contents: Vec<Vec<String>> = files
+        .iter()
+        .map(|file| read_input_lines(Some(file)))
+        .collect::<Result<_, _>>()?;
+
+    // Number of lines in the longest file, as shorter files have empty fields
+    let num_lines: usize = contents.iter().map(Vec::len).max().unwrap_or(0);
+
+    for line_index in 0..num_lines {
+        let mut line: String = String::new();
+
+        for (file_index, lines) in contents.iter().enumerate() {
+            if file_index > 0 {
+                line.push(delimiters[(file_index - 1) % delimiters.len()]);
+            }
+            if let Some(field) = lines.get(line_index) {
+                line.push_str(field);
+            }
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{command_directory, temp_directory, EnvGuard};
+    use crate::utils::parse_line;
+    use std::fs::{create_dir_all, read_to_string, set_permissions, write, Permissions};
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    use std::process::Child;
+
+    /// Splits a line into tokens
+    fn tokens(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    /// Runs a line holding operators as the shell does once a builtin has handed it to the
+    /// program of the same name
+    ///
+    /// # Return value
+    ///
+    /// The exit status of the line
+    fn run_as_program(line: &str) -> i32 {
+        let line: Vec<String> = tokens(line);
+        let mut path_cache: PathCache = PathCache::new();
+        assert!(runs_as_program(&line, &mut path_cache));
+
+        let mut stages: Vec<Child> = Vec::new();
+        let mut process: Command = parse_line(&line, None, &mut stages, &mut path_cache, false)
+            .unwrap()
+            .unwrap();
+        execute(&mut process, stages, &ShellOptions::new(), true, false).unwrap()
+    }
+
+    #[test]
+    fn redirected_builtins_run_the_program_on_path() {
+        let mut env: EnvGuard = EnvGuard::lock();
+        env.set("PATH", command_directory("shadowed", &["rev", "cd"]));
+        let mut path_cache: PathCache = PathCache::new();
+
+        assert!(runs_as_program(&tokens("rev f.txt > out"), &mut path_cache));
+        assert!(!runs_as_program(&tokens("rev f.txt"), &mut path_cache));
+
+        // Builtins that work on the shell itself, or have no program to stand in for, stay builtins
+        assert!(!runs_as_program(&tokens("cd /tmp && ls"), &mut path_cache));
+        assert!(!runs_as_program(
+            &tokens("tac f.txt > out"),
+            &mut path_cache
+        ));
+    }
+
+    #[test]
+    fn cat_with_input_redirected_runs_the_program() {
+        let mut env: EnvGuard = EnvGuard::lock();
+        env.set("PATH", command_directory("cat", &["cat"]));
+        let mut path_cache: PathCache = PathCache::new();
+
+        let heredoc: Vec<String> = vec![
+            String::from("cat"),
+            String::from("<<"),
+            String::from("line one\nline two"),
+        ];
+        assert!(runs_as_program(&heredoc, &mut path_cache));
+        assert!(runs_as_program(&tokens("cat < f.txt"), &mut path_cache));
+    }
+
+    #[test]
+    fn nl_pipes_into_the_next_command() {
+        let _env: EnvGuard = EnvGuard::lock();
+        let directory: PathBuf = temp_directory("nl");
+        let (input, out): (PathBuf, PathBuf) = (directory.join("f.txt"), directory.join("out"));
+        write(&input, "first\n\nsecond\n").unwrap();
+
+        let line: String = format!("nl {} | head -1 > {}", input.display(), out.display());
+        assert_eq!(run_as_program(&line), 0);
+        assert_eq!(read_to_string(&out).unwrap(), "     1\tfirst\n");
+    }
+
+    #[test]
+    fn every_redirected_text_builtin_runs_the_program() {
+        let programs: [&str; 10] = [
+            "column", "paste", "strings", "file", "split", "diff", "more", "less", "whoami",
+            "hostname",
+        ];
+        let mut env: EnvGuard = EnvGuard::lock();
+        env.set("PATH", command_directory("text", &programs));
+        let mut path_cache: PathCache = PathCache::new();
+
+        for program in programs {
+            let line: Vec<String> = tokens(&(program.to_owned() + " -t f.txt > out"));
+            assert!(runs_as_program(&line, &mut path_cache), "{}", program);
+        }
+    }
+
+    #[test]
+    fn undo_restores_a_file_removed_to_the_trash() {
+        let mut env: EnvGuard = EnvGuard::lock();
+        let directory: PathBuf = temp_directory("undo-trash");
+        env.set("HOME", &directory);
+
+        let file: PathBuf = directory.join("notes.txt");
+        write(&file, "data").unwrap();
+        let mut config: Config = Config::new();
+        config.set("rm_trash", "true");
+        let mut undo_log: UndoLog = UndoLog::new();
+
+        let rm: Vec<String> = tokens(&format!("rm {}", file.display()));
+        file_remove_builtin(&rm, &mut undo_log, &config).unwrap();
+        assert!(!file.exists());
+
+        undo_builtin(&tokens("undo"), &mut undo_log).unwrap();
+        assert_eq!(read_to_string(&file).unwrap(), "data");
+    }
+
+    #[test]
+    fn undo_keeps_a_created_file_that_was_written_to() {
+        let directory: PathBuf = temp_directory("undo-touch");
+        let (empty, written): (PathBuf, PathBuf) =
+            (directory.join("empty"), directory.join("written"));
+        let mut undo_log: UndoLog = UndoLog::new();
+
+        let touch: Vec<String> =
+            tokens(&format!("touch {} {}", empty.display(), written.display()));
+        touch_builtin(&touch, &mut undo_log).unwrap();
+        write(&written, "data").unwrap();
+
+        undo_builtin(&tokens("undo"), &mut undo_log).unwrap();
+        assert!(!empty.exists());
+        assert_eq!(read_to_string(&written).unwrap(), "data");
+    }
+
+    #[test]
+    fn permanent_rm_leaves_nothing_to_undo() {
+        let directory: PathBuf = temp_directory("undo-rm");
+        let file: PathBuf = directory.join("a");
+        let mut undo_log: UndoLog = UndoLog::new();
+
+        touch_builtin(&tokens(&format!("touch {}", file.display())), &mut undo_log).unwrap();
+        let rm: Vec<String> = tokens(&format!("rm {}", file.display()));
+        file_remove_builtin(&rm, &mut undo_log, &Config::new()).unwrap();
+
+        let error: Error = undo_builtin(&tokens("undo"), &mut undo_log).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn names_after_a_double_dash_are_files_even_if_they_look_like_flags() {
+        let mut guard: EnvGuard = EnvGuard::lock();
+        let directory: PathBuf = temp_directory("rm-dashes");
+        guard.set_current_dir(&directory);
+        write("-r.txt", "").unwrap();
+        let mut undo_log: UndoLog = UndoLog::new();
+
+        // Without "--" the name is an unknown flag, and nothing is removed
+        let error: Error =
+            file_remove_builtin(&tokens("rm -r.txt"), &mut undo_log, &Config::new()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        assert!(directory.join("-r.txt").exists());
+
+        file_remove_builtin(&tokens("rm -- -r.txt"), &mut undo_log, &Config::new()).unwrap();
+        assert!(!directory.join("-r.txt").exists());
+    }
+
+    #[test]
+    fn symlinks_are_described_by_their_target_only_when_followed() {
+        let directory: PathBuf = temp_directory("ls-links");
+        let (link, dangling): (PathBuf, PathBuf) =
+            (directory.join("link"), directory.join("dangling"));
+        symlink(&directory, &link).unwrap();
+        symlink(directory.join("missing"), &dangling).unwrap();
+
+        let follow: ListOptions = parse_list_options(&["-L"]).unwrap();
+        let no_follow: ListOptions = parse_list_options(&["-P"]).unwrap();
+        assert!(entry_metadata(&link, &follow).unwrap().is_dir());
+        assert!(entry_metadata(&link, &no_follow).unwrap().is_symlink());
+
+        // A link to nothing is still listed, as the link itself
+        assert!(entry_metadata(&dangling, &follow).unwrap().is_symlink());
+    }
+
+    #[test]
+    fn entries_on_another_device_are_skipped() {
+        let directory: PathBuf = temp_directory("one-file-system");
+        write(directory.join("file"), "").unwrap();
+        let device: u64 = symlink_metadata(&directory).unwrap().dev();
+
+        // Entries on a different device from the one being removed are kept
+        assert!(!remove_on_device(&directory, device + 1).unwrap());
+        assert!(directory.join("file").exists());
+
+        assert!(remove_on_device(&directory, device).unwrap());
+        assert!(!directory.exists());
+    }
+
+    #[test]
+    fn entries_are_named_by_their_last_component() {
+        let options: ListOptions = ListOptions::default();
+
+        assert_eq!(entry_name(Path::new("./notes.txt"), &options), "notes.txt");
+        assert_eq!(
+            entry_name(Path::new("src/nested/deeper/main.rs"), &options),
+            "main.rs"
+        );
+        assert_eq!(
+            entry_name(Path::new("/usr/local/bin/gecko"), &options),
+            "gecko"
+        );
+        assert_eq!(entry_name(Path::new("/"), &options), "/");
+    }
+
+    #[test]
+    fn combined_listings_name_entries_by_path() {
+        let options: ListOptions = parse_list_options(&["--no-headers"]).unwrap();
+
+        assert_eq!(entry_name(Path::new("./notes.txt"), &options), "notes.txt");
+        assert_eq!(
+            entry_name(Path::new("src/nested/main.rs"), &options),
+            "src/nested/main.rs"
+        );
+        assert_eq!(
+            entry_name(Path::new("/usr/bin/env"), &options),
+            "/usr/bin/env"
+        );
+    }
+
+    #[test]
+    fn entries_get_the_configured_icon_or_the_fallback() {
+        let directory: PathBuf = temp_directory("icons");
+        let (source, notes, script): (PathBuf, PathBuf, PathBuf) = (
+            directory.join("main.RS"),
+            directory.join("notes.txt"),
+            directory.join("run"),
+        );
+        write(&source, "").unwrap();
+        write(&notes, "").unwrap();
+        write(&script, "").unwrap();
+        set_permissions(&script, Permissions::from_mode(0o755)).unwrap();
+
+        let icon_map: HashMap<String, String> = parse_icon_map("dir=D, rs=R ,exec=X,default=?");
+        let icon = |path: &Path| -> String {
+            let name: String = entry_name(path, &ListOptions::default());
+            entry_icon(&name, &metadata(path).unwrap(), &icon_map).to_string()
+        };
+
+        assert_eq!(icon(&directory), "D");
+        assert_eq!(icon(&source), "R");
+        assert_eq!(icon(&script), "X");
+        assert_eq!(icon(&notes), "?");
+
+        // Without a default, unknown entries are padded to the width of an icon
+        let no_default: HashMap<String, String> = parse_icon_map("rs=R");
+        assert_eq!(
+            entry_icon("notes.txt", &metadata(&notes).unwrap(), &no_default),
+            " "
+        );
+    }
+
+    #[test]
+    fn cd_loads_the_history_of_the_new_directory() {
+        let mut guard: EnvGuard = EnvGuard::lock();
+        let home: PathBuf = temp_directory("cd-history");
+        let (project, other): (PathBuf, PathBuf) = (home.join("project"), home.join("other"));
+        create_dir_all(&project).unwrap();
+        create_dir_all(&other).unwrap();
+        guard.set("HOME", &home);
+        guard.set_current_dir(&project);
+
+        // An earlier session runs a command in the project
+        let mut earlier: History = History::new();
+        earlier.enable_per_directory().unwrap();
+        earlier.add_to_history(tokens("make test").into()).unwrap();
+        earlier.save().unwrap();
+
+        let mut history: History = History::new();
+        guard.set_current_dir(&other);
+        history.enable_per_directory().unwrap();
+        assert!(history.get(1).is_none());
+
+        let cd_project: Vec<String> = vec![String::from("cd"), project.display().to_string()];
+        change_dir_builtin(&cd_project, &mut history, &Config::new()).unwrap();
+        assert_eq!(history.get(1).unwrap().as_ref(), tokens("make test"));
+
+        let cd_other: Vec<String> = vec![String::from("cd"), other.display().to_string()];
+        change_dir_builtin(&cd_other, &mut history, &Config::new()).unwrap();
+        assert!(history.get(1).is_none());
+    }
+
+    #[test]
+    fn diffs_mark_removed_and_added_lines() {
+        let old_lines: Vec<String> = tokens("a b c d");
+        let new_lines: Vec<String> = tokens("a c d e");
+        assert_eq!(
+            diff_lines(&old_lines, &new_lines),
+            [" a", "-b", " c", " d", "+e"]
+        );
+
+        // Against an empty file every line is removed or added
+        assert_eq!(diff_lines(&tokens("a b"), &[]), ["-a", "-b"]);
+        assert_eq!(diff_lines(&[], &tokens("a b")), ["+a", "+b"]);
+        assert!(diff_lines(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn long_names_are_fitted_to_the_name_column() {
+        let short: String = "a".repeat(NAME_WIDTH);
+        assert_eq!(fit_name(&short, "ellipsize"), [short.as_str()]);
+        assert_eq!(fit_name(&short, "wrap"), [short.as_str()]);
+
+        let long: String = "a".repeat(NAME_WIDTH) + "bc";
+        assert_eq!(
+            fit_name(&long, "ellipsize"),
+            ["a".repeat(NAME_WIDTH - 1) + "…"]
+        );
+        assert_eq!(
+            fit_name(&long, "wrap"),
+            ["a".repeat(NAME_WIDTH), String::from("bc")]
+        );
+        assert_eq!(fit_name(&long, "false"), [long.as_str()]);
+
+        // Names are measured and cut in characters, not bytes
+        let accented: String = "é".repeat(NAME_WIDTH);
+        assert_eq!(fit_name(&accented, "ellipsize"), [accented.as_str()]);
+        let accented: String = "é".repeat(NAME_WIDTH + 1);
+        assert_eq!(
+            fit_name(&accented, "ellipsize"),
+            ["é".repeat(NAME_WIDTH - 1) + "…"]
+        );
+        assert_eq!(
+            fit_name(&accented, "wrap"),
+            ["é".repeat(NAME_WIDTH), String::from("é")]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use sha2::{Digest, Sha256};
+use std::io::{Error, Read};
+
+/// Hash functions that checksums can be computed with
+#[derive(Clone, Copy)]
+pub enum Algorithm {
+    Md5,
+    Sha256,
+}
+
+/// Computes the checksum of everything read from a reader
+///
+/// # Arguments
+///
+/// * `algorithm` - The hash function to use
+/// * `input` - The data to hash
+///
+/// # Return value
+///
+/// The checksum as lowercase hexadecimal
+pub fn checksum<R: Read>(algorithm: Algorithm, mut input: R) -> Result<String, Error> {
+    let mut md5: Md5 = Md5::new();
+    let mut sha256: Sha256 = Sha256::new();
+
+    // Data is hashed a block at a time so large files are not read into memory
+    let mut buffer: [u8; 8192] = [0; 8192];
+    loop {
+        let bytes_read: usize = input.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+
+        match algorithm {
+            Algorithm::Md5 => md5.update(&buffer[..bytes_read]),
+            Algorithm::Sha256 => sha256.update(&buffer[..bytes_read]),
+        }
+    }
+
+    let digest: Vec<u8> = match algorithm {
+        Algorithm::Md5 => md5.finalize().to_vec(),
+        Algorithm::Sha256 => sha256.finalize().to_vec(),
+    };
+
+    Ok(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+/// Per-round shift amounts of MD5 (RFC 1321)
+const MD5_SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9,
+    14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15,
+    21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+];
+
+/// An MD5 hash being computed, for which no crate is a dependency
+struct Md5 {
+    // The A, B, C, and D words of the digest
+    state: [u32; 4],
+    // Bytes not yet hashed, as MD5 hashes 64 bytes at a time
+    pending: Vec<u8>,
+    // Number of bytes given so far
+    length: u64,
+}
+
+impl Md5 {
+    fn new() -> Self {
+        Md5 {
+            state: [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476],
+            pending: Vec::with_capacity(64),
+            length: 0,
+        }
+    }
+
+    /// Adds data to the hash
+    fn update(&mut self, data: &[u8]) {
+        self.length = self.length.wrapping_add(data.len() as u64);
+        self.pending.extend_from_slice(data);
+
+        // Hash every complete block, keeping the remainder for later
+        let pending: Vec<u8> = std::mem::take(&mut self.pending);
+        let blocks = pending.chunks_exact(64);
+        self.pending = blocks.remainder().to_vec();
+        for block in blocks {
+            self.hash_block(block);
+        }
+    }
+
+    /// Pads the data and returns the digest
+    fn finalize(mut self) -> [u8; 16] {
+        let bit_length: u64 = self.length.wrapping_mul(8);
+
+        // A 1 bit, then zeros until 8 bytes short of a block, then the length in bits
+        let mut padding: Vec<u8> = vec![0x80];
+        while (self.pending.len() + padding.len()) % 64 != 56 {
+            padding.push(0);
+        }
+        padding.extend_from_slice(&bit_length.to_le_bytes());
+
+        // The length in the padding is of the data only
+        let length: u64 = self.length;
+        self.update(&padding);
+        self.length = length;
+
+        let mut digest: [u8; 16] = [0; 16];
+        for (word, bytes) in self.state.iter().zip(digest.chunks_mut(4)) {
+            bytes.copy_from_slice(&word.to_le_bytes());
+        }
+        digest
+    }
+
+    /// Mixes a 64 byte block into the state
+    fn hash_block(&mut self, block: &[u8]) {
+        let words: Vec<u32> = block
+            .chunks(4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+
+        let [mut a, mut b, mut c, mut d] = self.state;
+
+        for (i, shift) in MD5_SHIFTS.iter().enumerate() {
+            // Each round of 16 steps mixes the words in a different order
+            let (f, g): (u32, usize) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+
+            // The constants are the integer parts of 2^32 * |sin(i + 1)|
+            let constant: u32 = ((i as f64 + 1.0).sin().abs() * 4294967296.0) as u32;
+
+            let rotated: u32 = a
+                .wrapping_add(f)
+                .wrapping_add(constant)
+                .wrapping_add(words[g])
+                .rotate_left(*shift);
+
+            a = d;
+            d = c;
+            c = b;
+            b = b.wrapping_add(rotated);
+        }
+
+        self.state[0] = self.state[0].wrapping_add(a);
+        self.state[1] = self.state[1].wrapping_add(b);
+        self.state[2] = self.state[2].wrapping_add(c);
+        self.state[3] = self.state[3].wrapping_add(d);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn md5_matches_known_digests() {
+        assert_eq!(
+            checksum(Algorithm::Md5, "".as_bytes()).unwrap(),
+            "d41d8cd98f00b204e9800998ecf8427e"
+        );
+        assert_eq!(
+            checksum(Algorithm::Md5, "abc".as_bytes()).unwrap(),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+    }
+
+    #[test]
+    fn md5_spans_several_blocks() {
+        let input: Vec<u8> = vec![b'a'; 1_000_000];
+        assert_eq!(
+            checksum(Algorithm::Md5, input.as_slice()).unwrap(),
+            "7707d6ae4e027c70eea2a935c2296f21"
+        );
+    }
+
+    #[test]
+    fn sha256_matches_known_digest() {
+        assert_eq!(
+            checksum(Algorithm::Sha256, "abc".as_bytes()).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
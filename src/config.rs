@@ -5,8 +5,28 @@ pub struct Config {
     // ls settings
     directory_text_color: String,
     filename_text_color: String,
+    ls_icons: String,
+    ls_long_names: String,
     // error settings
     error_text_color: String,
+    error_verbosity: String,
+    // history settings
+    per_directory_history: String,
+    clear_history_on_exit: String,
+    // rm settings
+    rm_trash: String,
+    // line settings
+    command_separator: String,
+    // cd settings
+    cd_to_file_parent: String,
+    // terminal settings
+    set_title: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Config {
@@ -15,7 +35,18 @@ impl Config {
         Config {
             directory_text_color: String::from("42;125;211"),
             filename_text_color: String::from("192;192;192"),
+            ls_icons: String::from(
+                "dir=\u{f115},exec=\u{f489},rs=\u{e7a8},md=\u{f48a},png=\u{f1c5},default=\u{f15b}",
+            ),
+            ls_long_names: String::from("full"),
             error_text_color: String::from("255;0;0"),
+            error_verbosity: String::from("normal"),
+            per_directory_history: String::from("false"),
+            clear_history_on_exit: String::from("false"),
+            rm_trash: String::from("false"),
+            command_separator: String::from(";"),
+            cd_to_file_parent: String::from("false"),
+            set_title: String::from("false"),
         }
     }
 
@@ -28,33 +59,64 @@ impl Config {
         for line_result in reader.lines() {
             let line: String = line_result.unwrap();
 
-            if line.contains("#") {
-                println!("Found comment");
-            } else {
+            // Comments are skipped
+            if !line.contains("#") {
                 self.handle_settings(&line);
             }
         }
     }
 
     /// Handles the parsing of config file text
-    fn handle_settings(&mut self, line: &String) {
+    fn handle_settings(&mut self, line: &str) {
         let line_values = line.split(":").collect::<Vec<&str>>();
 
-        match line_values[0] {
-            "directory_text_color" => self.directory_text_color = line_values[1].to_string(),
-            "filename_text_color" => self.filename_text_color = line_values[1].to_string(),
-            "error_text_color" => self.error_text_color = line_values[1].to_string(),
-            &_ => println!("invalid line found -- {}", line_values[0]),
+        if !self.set(line_values[0], line_values.get(1).unwrap_or(&"")) {
+            println!("invalid line found -- {}", line_values[0]);
+        }
+    }
+
+    /// Sets the value of the given field
+    ///
+    /// # Return value
+    ///
+    /// False if there is no field with the given name, else true
+    pub fn set(&mut self, field_string: &str, value: &str) -> bool {
+        let value: String = value.to_string();
+
+        match field_string {
+            "directory_text_color" => self.directory_text_color = value,
+            "filename_text_color" => self.filename_text_color = value,
+            "ls_icons" => self.ls_icons = value,
+            "ls_long_names" => self.ls_long_names = value,
+            "error_text_color" => self.error_text_color = value,
+            "error_verbosity" => self.error_verbosity = value,
+            "per_directory_history" => self.per_directory_history = value,
+            "clear_history_on_exit" => self.clear_history_on_exit = value,
+            "rm_trash" => self.rm_trash = value,
+            "command_separator" => self.command_separator = value,
+            "cd_to_file_parent" => self.cd_to_file_parent = value,
+            "set_title" => self.set_title = value,
+            &_ => return false,
         }
+        true
     }
 
-    /// Gets the color value for the given field
+    /// Gets the value for the given field
     pub fn get(&self, field_string: &str) -> String {
-        return match field_string {
+        match field_string {
             "directory_text_color" => self.directory_text_color.clone(),
             "filename_text_color" => self.filename_text_color.clone(),
+            "ls_icons" => self.ls_icons.clone(),
+            "ls_long_names" => self.ls_long_names.clone(),
             "error_text_color" => self.error_text_color.clone(),
+            "error_verbosity" => self.error_verbosity.clone(),
+            "per_directory_history" => self.per_directory_history.clone(),
+            "clear_history_on_exit" => self.clear_history_on_exit.clone(),
+            "rm_trash" => self.rm_trash.clone(),
+            "command_separator" => self.command_separator.clone(),
+            "cd_to_file_parent" => self.cd_to_file_parent.clone(),
+            "set_title" => self.set_title.clone(),
             _ => String::from("No value for given field"),
-        };
+        }
     }
 }
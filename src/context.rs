@@ -0,0 +1,52 @@
+use crate::config::Config;
+use crate::dir_stack::DirStack;
+use crate::history::History;
+use crate::jobs::Jobs;
+use crate::options::ShellOptions;
+use crate::path_cache::PathCache;
+use crate::traps::Traps;
+use crate::undo::UndoLog;
+
+/// Everything the shell keeps between commands, which builtins read and change
+pub struct ShellContext {
+    // Every command entered, including in earlier sessions
+    pub history: History,
+    // Options changed with the 'set' builtin
+    pub options: ShellOptions,
+    // Locations of commands already found on PATH
+    pub path_cache: PathCache,
+    // Commands registered with the 'trap' builtin
+    pub traps: Traps,
+    // How to reverse the destructive builtins that have run
+    pub undo_log: UndoLog,
+    // Directories saved with the 'pushd' builtin
+    pub dir_stack: DirStack,
+    // Lines running in the background
+    pub jobs: Jobs,
+    // The shell's settings
+    pub config: Config,
+    // Whether a user is typing commands at a terminal, rather than them being piped in
+    pub interactive: bool,
+}
+
+impl ShellContext {
+    /// Create the state of a new session, with the history saved by earlier sessions
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The shell's settings
+    /// * `interactive` - Whether a user is typing commands at a terminal
+    pub fn new(config: Config, interactive: bool) -> Self {
+        ShellContext {
+            history: History::new(),
+            options: ShellOptions::new(),
+            path_cache: PathCache::new(),
+            traps: Traps::new(),
+            undo_log: UndoLog::new(),
+            dir_stack: DirStack::new(),
+            jobs: Jobs::new(),
+            config,
+            interactive,
+        }
+    }
+}
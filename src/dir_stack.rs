@@ -0,0 +1,188 @@
+use std::env;
+use std::io::{Error, ErrorKind};
+use std::iter::once;
+use std::path::PathBuf;
+
+/// Directories saved by 'pushd' for 'popd' to return to
+pub struct DirStack {
+    // Saved directories, the top of the stack first
+    directories: Vec<PathBuf>,
+}
+
+impl Default for DirStack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DirStack {
+    pub fn new() -> Self {
+        DirStack {
+            directories: Vec::new(),
+        }
+    }
+
+    /// Saves a directory on top of the stack
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The directory to save
+    pub fn push(&mut self, directory: PathBuf) {
+        self.directories.insert(0, directory);
+    }
+
+    /// Gets the directory on top of the stack
+    pub fn top(&self) -> Result<&PathBuf, Error> {
+        self.directories
+            .first()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "Directory stack empty"))
+    }
+
+    /// Removes the directory on top of the stack
+    pub fn pop(&mut self) -> Result<PathBuf, Error> {
+        self.top()?;
+        Ok(self.directories.remove(0))
+    }
+
+    /// Replaces the directory on top of the stack
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The directory to save in its place
+    pub fn replace_top(&mut self, directory: PathBuf) -> Result<(), Error> {
+        self.top()?;
+        self.directories[0] = directory;
+        Ok(())
+    }
+
+    /// Gets a directory by its position in the listing, where 1 is the top of the stack
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The position of the directory, as shown by 'dirs -v'
+    pub fn get(&self, position: usize) -> Result<&PathBuf, Error> {
+        position
+            .checked_sub(1)
+            .and_then(|index| self.directories.get(index))
+            .ok_or_else(|| out_of_range(position))
+    }
+
+    /// Rotates the listing so the directory at a position is first, which makes it the current
+    /// directory and leaves it off the stack
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The position of the directory, as shown by 'dirs -v'
+    /// * `current_directory` - The directory that was current before the rotation
+    pub fn rotate(&mut self, position: usize, current_directory: PathBuf) -> Result<(), Error> {
+        self.get(position)?;
+
+        self.directories.insert(0, current_directory);
+        self.directories.rotate_left(position);
+        self.directories.remove(0);
+        Ok(())
+    }
+
+    /// Removes a directory by its position in the listing, where 1 is the top of the stack
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The position of the directory, as shown by 'dirs -v'
+    pub fn remove(&mut self, position: usize) -> Result<PathBuf, Error> {
+        self.get(position)?;
+        Ok(self.directories.remove(position - 1))
+    }
+
+    /// Removes every directory from the stack
+    pub fn clear(&mut self) {
+        self.directories.clear();
+    }
+
+    /// Prints the current directory followed by the stack, one per line with its position
+    pub fn display_numbered(&self) -> Result<(), Error> {
+        for (index, directory) in self.listing()?.iter().enumerate() {
+            println!("{:2}  {}", index, directory);
+        }
+        Ok(())
+    }
+
+    /// Prints the current directory followed by the stack, on one line as in other shells
+    pub fn display(&self) -> Result<(), Error> {
+        let listing: Vec<String> = self.listing()?;
+        println!("{}", listing.join(" "));
+        Ok(())
+    }
+
+    /// Gets the current directory followed by the stack, with the home directory shown as `~`
+    fn listing(&self) -> Result<Vec<String>, Error> {
+        let home: Option<PathBuf> = env::var("HOME").ok().map(PathBuf::from);
+
+        Ok(once(&env::current_dir()?)
+            .chain(self.directories.iter())
+            .map(|directory| match &home {
+                Some(home) => match directory.strip_prefix(home) {
+                    Ok(rest) if rest.as_os_str().is_empty() => String::from("~"),
+                    Ok(rest) => "~/".to_owned() + &rest.display().to_string(),
+                    Err(_) => directory.display().to_string(),
+                },
+                None => directory.display().to_string(),
+            })
+            .collect())
+    }
+}
+
+/// Creates the error for a position past the end of the listing
+fn out_of_range(position: usize) -> Error {
+    let error_message: String = format!("+{}: directory stack index out of range", position);
+    Error::new(ErrorKind::InvalidInput, error_message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// Creates a stack holding the given directories, the top of the stack first
+    fn stack_of(directories: &[&str]) -> DirStack {
+        let mut stack: DirStack = DirStack::new();
+        for directory in directories.iter().rev() {
+            stack.push(PathBuf::from(directory));
+        }
+        stack
+    }
+
+    #[test]
+    fn push_and_pop_work_from_the_top() {
+        let mut stack: DirStack = stack_of(&["/b", "/a"]);
+        assert_eq!(stack.top().unwrap(), Path::new("/b"));
+        assert_eq!(stack.pop().unwrap(), Path::new("/b"));
+        assert_eq!(stack.pop().unwrap(), Path::new("/a"));
+        assert!(stack.pop().is_err());
+    }
+
+    #[test]
+    fn positions_start_at_one() {
+        let stack: DirStack = stack_of(&["/b", "/a"]);
+        assert_eq!(stack.get(1).unwrap(), Path::new("/b"));
+        assert_eq!(stack.get(2).unwrap(), Path::new("/a"));
+        assert!(stack.get(0).is_err());
+        assert!(stack.get(3).is_err());
+    }
+
+    #[test]
+    fn rotate_leaves_the_chosen_directory_off_the_stack() {
+        let mut stack: DirStack = stack_of(&["/b", "/a"]);
+        stack.rotate(2, PathBuf::from("/current")).unwrap();
+
+        // '/a' is now the current directory, and the listing goes on in the same cyclic order
+        assert_eq!(stack.directories, [Path::new("/current"), Path::new("/b")]);
+    }
+
+    #[test]
+    fn remove_takes_out_one_directory() {
+        let mut stack: DirStack = stack_of(&["/c", "/b", "/a"]);
+        assert_eq!(stack.remove(2).unwrap(), Path::new("/b"));
+        assert_eq!(stack.directories, [Path::new("/c"), Path::new("/a")]);
+        assert!(stack.remove(3).is_err());
+    }
+}
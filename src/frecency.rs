@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How often and how recently a directory was changed to
+struct Visits {
+    // Number of times the directory was changed to
+    count: u64,
+    // When the directory was last changed to, in seconds since the Unix epoch
+    last_visit: u64,
+}
+
+/// Directories that 'cd' has changed to, kept in `~/.gecko_frecency` so 'z' can jump to them
+pub struct Frecency {
+    // Each directory mapped to its visits
+    directories: BTreeMap<PathBuf, Visits>,
+}
+
+impl Frecency {
+    /// Loads the recorded directories, of which there are none if the file does not exist yet
+    pub fn load() -> Result<Self, Error> {
+        let mut frecency: Frecency = Frecency {
+            directories: BTreeMap::new(),
+        };
+
+        let frecency_file: File = match File::open(frecency_file()) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(frecency),
+            Err(e) => return Err(e),
+        };
+
+        // Each line is a directory, its count, and its last visit separated by tabs. Lines that
+        // cannot be read are skipped rather than losing every other directory.
+        for line in BufReader::new(frecency_file).lines() {
+            let line: String = line?;
+            let fields: Vec<&str> = line.split('\t').collect();
+
+            if let [directory, count, last_visit] = fields[..] {
+                if let (Ok(count), Ok(last_visit)) = (count.parse(), last_visit.parse()) {
+                    frecency
+                        .directories
+                        .insert(PathBuf::from(directory), Visits { count, last_visit });
+                }
+            }
+        }
+        Ok(frecency)
+    }
+
+    /// Records a visit to a directory
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The directory that was changed to
+    pub fn visit(&mut self, directory: &Path) -> Result<(), Error> {
+        let now: u64 = now();
+
+        let visits: &mut Visits =
+            self.directories
+                .entry(directory.to_path_buf())
+                .or_insert(Visits {
+                    count: 0,
+                    last_visit: now,
+                });
+        visits.count += 1;
+        visits.last_visit = now;
+
+        self.save()
+    }
+
+    /// Finds the highest scoring directory whose path holds every pattern. Directories that no
+    /// longer exist are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - Text that the path must contain
+    ///
+    /// # Return value
+    ///
+    /// The best matching directory
+    pub fn best_match(&self, patterns: &[String]) -> Result<PathBuf, Error> {
+        let now: u64 = now();
+
+        self.directories
+            .iter()
+            .filter(|(directory, _)| {
+                let path: String = directory.display().to_string();
+                patterns
+                    .iter()
+                    .all(|pattern| path.contains(pattern.as_str()))
+                    && directory.is_dir()
+            })
+            .map(|(directory, visits)| (score(visits, now), directory))
+            // Ties go to the first directory in sorted order
+            .fold(
+                None,
+                |best: Option<(f64, &PathBuf)>, (score, directory)| match best {
+                    Some((best_score, _)) if best_score >= score => best,
+                    _ => Some((score, directory)),
+                },
+            )
+            .map(|(_, directory)| directory.clone())
+            .ok_or_else(|| {
+                let error_message: String = patterns.join(" ") + ": no matching directory";
+                Error::new(ErrorKind::NotFound, error_message)
+            })
+    }
+
+    /// Writes every directory to the frecency file
+    fn save(&self) -> Result<(), Error> {
+        let mut file: File = File::create(frecency_file())?;
+
+        for (directory, visits) in &self.directories {
+            writeln!(
+                file,
+                "{}\t{}\t{}",
+                directory.display(),
+                visits.count,
+                visits.last_visit
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Scores a directory by how often it was visited, weighted by how recently
+///
+/// # Arguments
+///
+/// * `visits` - The directory's visits
+/// * `now` - The current time, in seconds since the Unix epoch
+fn score(visits: &Visits, now: u64) -> f64 {
+    let age: u64 = now.saturating_sub(visits.last_visit);
+
+    // The same weights as the 'z' script
+    let weight: f64 = if age < 60 * 60 {
+        4.0
+    } else if age < 24 * 60 * 60 {
+        2.0
+    } else if age < 7 * 24 * 60 * 60 {
+        0.5
+    } else {
+        0.25
+    };
+    visits.count as f64 * weight
+}
+
+/// The current time, in seconds since the Unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Finds the file holding the visited directories, `~/.gecko_frecency`
+fn frecency_file() -> PathBuf {
+    let home: String = env::var("HOME").unwrap_or_else(|_| String::from("."));
+    PathBuf::from(home).join(".gecko_frecency")
+}
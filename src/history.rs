@@ -1,20 +1,255 @@
+use chrono::prelude::{DateTime, Local};
+use std::collections::HashMap;
+use std::env;
+use std::fs::{create_dir_all, remove_dir_all, remove_file, File, OpenOptions};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// When a command was entered, which is not known for every command of earlier sessions
+type EntryTime = Option<DateTime<Local>>;
+
 pub struct History {
-    commands: Vec<Vec<String>>,
+    commands: Vec<Rc<[String]>>,
+    // When each command in `commands` was entered, if it was entered in this session
+    times: Vec<EntryTime>,
+    // How many of `commands` were loaded from earlier sessions, before those of this session
+    loaded: usize,
+    // When this session started
+    started: DateTime<Local>,
+    // Whether history is scoped to the current working directory
+    per_directory: bool,
+    // Commands run in the current working directory in earlier sessions, when history is scoped to
+    // it, with when they were entered if that was saved
+    directory_commands: Vec<(Rc<[String]>, EntryTime)>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
 }
+
 impl History {
-    pub const fn new() -> Self {
-        History {
+    /// Create history object holding the commands saved by earlier sessions
+    pub fn new() -> Self {
+        let mut history: History = History {
             commands: Vec::new(),
+            times: Vec::new(),
+            loaded: 0,
+            started: Local::now(),
+            per_directory: false,
+            directory_commands: Vec::new(),
+        };
+
+        // There is nothing to load before the first session has been saved
+        if let Ok(history_file) = File::open(history_file()) {
+            for line in BufReader::new(history_file).lines().map_while(Result::ok) {
+                let (command, time): (Vec<String>, EntryTime) = from_timed_record(&line);
+                if !command.is_empty() {
+                    history.commands.push(command.into());
+                    history.times.push(time);
+                }
+            }
+        }
+        history.loaded = history.commands.len();
+        history
+    }
+
+    /// Saves every command to `~/.gecko_history`, one per line after the time it was entered if
+    /// known, so later sessions can load them. When history is scoped per directory, the commands
+    /// of this session are saved to their directories' files instead, so only those loaded from
+    /// earlier sessions are kept.
+    pub fn save(&self) -> Result<(), Error> {
+        let mut file: File = File::create(history_file())?;
+
+        let saved: usize = if self.per_directory {
+            self.loaded
+        } else {
+            self.commands.len()
+        };
+
+        for (command, time) in self.commands.iter().zip(&self.times).take(saved) {
+            if !command.is_empty() {
+                writeln!(file, "{}", to_timed_record(command, *time))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Scopes the displayed history to the current working directory: its commands from earlier
+    /// sessions are shown merged with the global history, followed by every command of this
+    /// session. Commands are saved to a file per directory, rather than to the global history, so
+    /// they are recalled when returning to the directory in a later session and not elsewhere.
+    pub fn enable_per_directory(&mut self) -> Result<(), Error> {
+        self.per_directory = true;
+        self.change_directory()
+    }
+
+    /// Loads the history of the new working directory after a directory change
+    pub fn change_directory(&mut self) -> Result<(), Error> {
+        if !self.per_directory {
+            return Ok(());
+        }
+
+        self.directory_commands.clear();
+
+        // A directory that has never had a command run in it has no history yet
+        let history_file: File = match File::open(directory_history_file()?) {
+            Ok(file) => file,
+            Err(_) => return Ok(()),
+        };
+
+        for line in BufReader::new(history_file).lines() {
+            let (command, time): (Vec<String>, EntryTime) = from_timed_record(&line?);
+
+            // Commands of this session are already among the session's own
+            if !command.is_empty() && time.is_none_or(|time| time < self.started) {
+                self.directory_commands.push((command.into(), time));
+            }
         }
+        Ok(())
     }
 
     /// Adds new command to the tracked history
     ///
     /// # Arguments
     ///
-    /// * 'command' - A new command to save to the history
-    pub fn add_to_history(&mut self, command: &Vec<String>) {
-        self.commands.push(command.clone());
+    /// * 'command' - A new command to save to the history, shared with the caller instead of copied
+    ///
+    /// # Return value
+    ///
+    /// An error if the command could not be saved for this directory, in which case it is still
+    /// added to the history
+    pub fn add_to_history(&mut self, command: Rc<[String]>) -> Result<(), Error> {
+        let time: DateTime<Local> = Local::now();
+
+        let saved: Result<(), Error> = if self.per_directory && !command.is_empty() {
+            append_to_directory_history(&command, Some(time))
+        } else {
+            Ok(())
+        };
+
+        self.commands.push(command);
+        self.times.push(Some(time));
+        saved
+    }
+
+    /// Forgets every command, including those saved to files
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.commands.clear();
+        self.times.clear();
+        self.loaded = 0;
+        self.directory_commands.clear();
+
+        // Saved history that does not exist is already cleared
+        match remove_file(history_file()) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        match remove_dir_all(directory_history_root()) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Replaces a '!' history reference at the start of a line with the command it refers to.
+    /// `!!` refers to the last command, `!<n>` to the nth command as numbered by 'history', and
+    /// `!<prefix>` to the most recent command starting with `<prefix>`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - The entered line
+    ///
+    /// # Return value
+    ///
+    /// The line to run, which is `tokens` unchanged if it has no history reference
+    pub fn expand(&self, tokens: Rc<[String]>) -> Result<Rc<[String]>, Error> {
+        let reference: &str = match tokens.first() {
+            Some(first) if first.len() > 1 && first.starts_with('!') => first,
+            _ => return Ok(tokens),
+        };
+
+        let recalled: Option<&Rc<[String]>> = match &reference[1..] {
+            "!" => self
+                .displayed_commands()
+                .last()
+                .map(|(command, _)| *command),
+            event => match event.parse::<usize>() {
+                Ok(index) => self.get(index),
+                Err(_) => self.find_by_prefix(event),
+            },
+        };
+
+        let recalled: &Rc<[String]> = recalled.ok_or_else(|| {
+            let error_message: String = reference.to_owned() + ": event not found";
+            Error::new(ErrorKind::NotFound, error_message)
+        })?;
+
+        // Anything after the reference is added to the end of the recalled command
+        let expanded: Vec<String> = recalled.iter().chain(&tokens[1..]).cloned().collect();
+
+        // Show the command being run, as it was not what was typed
+        println!("{}", expanded.join(" "));
+
+        Ok(expanded.into())
+    }
+
+    /// Gets a command by the number 'history' shows it with
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The number of the command, starting from 1
+    pub fn get(&self, index: usize) -> Option<&Rc<[String]>> {
+        self.displayed_commands()
+            .get(index.checked_sub(1)?)
+            .map(|(command, _)| *command)
+    }
+
+    /// Finds the most recent command whose name or whole line starts with a prefix
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - The start of the command to find
+    fn find_by_prefix(&self, prefix: &str) -> Option<&Rc<[String]>> {
+        self.displayed_commands()
+            .into_iter()
+            .rev()
+            .map(|(command, _)| command)
+            .find(|command| {
+                command.first().is_some_and(|name| name.starts_with(prefix))
+                    || command.join(" ").starts_with(prefix)
+            })
+    }
+
+    /// Gets the commands that 'history' shows, with when they were entered if known. When history
+    /// is scoped per directory, the global history and the working directory's commands from
+    /// earlier sessions are merged in the order they were entered, with commands saved before
+    /// times were added first, and followed by every command of this session.
+    fn displayed_commands(&self) -> Vec<(&Rc<[String]>, EntryTime)> {
+        let every_command = self.commands.iter().zip(self.times.iter().copied());
+
+        if self.per_directory {
+            let mut earlier: Vec<(&Rc<[String]>, EntryTime)> = every_command
+                .clone()
+                .take(self.loaded)
+                .chain(
+                    self.directory_commands
+                        .iter()
+                        .map(|(command, time)| (command, *time)),
+                )
+                .collect();
+
+            // The sort is stable, so commands entered at the same time keep their order
+            earlier.sort_by_key(|(_, time)| *time);
+
+            earlier
+                .into_iter()
+                .chain(every_command.skip(self.loaded))
+                .collect()
+        } else {
+            every_command.collect()
+        }
     }
 
     /// Prints the complete history
@@ -22,29 +257,35 @@ impl History {
         // Used to display what number a command is in the history
         let mut count: usize = 1;
 
-        for command in &self.commands {
+        for (command, _) in self.displayed_commands() {
             println!("{} > {:?}", count, &command.join(" "));
 
             count = &count + 1;
         }
     }
 
-    /// Prints the last n commands in the history
+    /// Prints the last n commands in the history, numbered as 'history' numbers them
     ///
     /// # Arguments
     ///
     /// * 'num' - The number of commands to display
     pub fn display_num_commands(&self, num: usize) {
+        let commands: Vec<&Rc<[String]>> = self
+            .displayed_commands()
+            .into_iter()
+            .map(|(command, _)| command)
+            .collect();
+
         // Number of commands to display
         let mut num_commands: usize = num;
 
         // Check if the received value is to large
-        if num > self.commands.len() {
-            num_commands = self.commands.len();
+        if num > commands.len() {
+            num_commands = commands.len();
         }
 
         // Index of the commands to be printed
-        let mut current_index: usize = self.commands.len() - num_commands;
+        let mut current_index: usize = commands.len() - num_commands;
 
         // Number of commands that have been printed
         let mut count: usize = 0;
@@ -53,12 +294,389 @@ impl History {
         while count != num_commands {
             println!(
                 "{} > {:?}",
-                current_index,
-                &self.commands[current_index].join(" ")
+                current_index + 1,
+                &commands[current_index].join(" ")
             );
 
             current_index = &current_index + 1;
             count = &count + 1;
         }
     }
+
+    /// Prints every command 'history' shows as a JSON object per line
+    pub fn export_json(&self) {
+        for line in self.json_lines() {
+            println!("{}", line);
+        }
+    }
+
+    /// Formats every command 'history' shows as a JSON object, with the number 'history' shows it
+    /// with and the time it was entered, which is null if not known
+    fn json_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+
+        for (index, (command, time)) in self.displayed_commands().into_iter().enumerate() {
+            // Empty lines keep their place in the numbering but are not commands
+            if command.is_empty() {
+                continue;
+            }
+
+            let time: String = match time {
+                Some(time) => "\"".to_owned() + &time.to_rfc3339() + "\"",
+                None => String::from("null"),
+            };
+
+            lines.push(format!(
+                "{{\"index\":{},\"command\":\"{}\",\"time\":{}}}",
+                index + 1,
+                json_escape(&command.join(" ")),
+                time
+            ));
+        }
+        lines
+    }
+
+    /// Prints how many times each command was used, most used first
+    ///
+    /// # Arguments
+    ///
+    /// * 'limit' - The number of commands to display, or all of them if `None`
+    pub fn display_stats(&self, limit: Option<usize>) {
+        for (name, count) in self
+            .command_counts()
+            .iter()
+            .take(limit.unwrap_or(usize::MAX))
+        {
+            println!("{:>5}  {}", count, name);
+        }
+    }
+
+    /// Counts how many times each command was used. Commands are counted by their first token, so
+    /// 'ls -a' and 'ls' are both 'ls'.
+    ///
+    /// # Return value
+    ///
+    /// Each command's name and count, most used first, with ties sorted by name so the order is
+    /// stable
+    fn command_counts(&self) -> Vec<(&str, usize)> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for (command, _) in self.displayed_commands() {
+            if let Some(name) = command.first() {
+                *counts.entry(name).or_insert(0) += 1;
+            }
+        }
+
+        let mut stats: Vec<(&str, usize)> = counts.into_iter().collect();
+        stats.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        stats
+    }
+}
+
+/// Finds the file holding the history of the current working directory,
+/// `~/.gecko_directory_history/<hash of the directory's absolute path>`
+fn directory_history_file() -> Result<PathBuf, Error> {
+    let directory: PathBuf = env::current_dir()?;
+
+    // FNV-1a, used because its output is the same for every build of the shell
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in directory.to_string_lossy().bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    Ok(directory_history_root().join(format!("{:016x}", hash)))
+}
+
+/// Finds the directory holding the history files of every directory,
+/// `~/.gecko_directory_history`
+fn directory_history_root() -> PathBuf {
+    home_directory().join(".gecko_directory_history")
+}
+
+/// Finds the file holding the history of every session, `~/.gecko_history`
+fn history_file() -> PathBuf {
+    home_directory().join(".gecko_history")
+}
+
+/// Finds the user's home directory, or the current directory if HOME is not set
+fn home_directory() -> PathBuf {
+    PathBuf::from(env::var("HOME").unwrap_or_else(|_| String::from(".")))
+}
+
+/// Saves a command to the end of the current working directory's history file
+///
+/// # Arguments
+///
+/// * `command` - The command to save
+/// * `time` - When the command was entered
+fn append_to_directory_history(command: &[String], time: EntryTime) -> Result<(), Error> {
+    let history_file: PathBuf = directory_history_file()?;
+
+    if let Some(history_directory) = history_file.parent() {
+        create_dir_all(history_directory)?;
+    }
+
+    let mut file: File = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history_file)?;
+    writeln!(file, "{}", to_timed_record(command, time))
+}
+
+/// Formats a command as a line of a history file, after the time it was entered and a tab if the
+/// time is known
+///
+/// # Arguments
+///
+/// * `command` - The command to save
+/// * `time` - When the command was entered
+fn to_timed_record(command: &[String], time: EntryTime) -> String {
+    match time {
+        Some(time) => time.to_rfc3339() + "\t" + &to_record(command),
+        None => to_record(command),
+    }
+}
+
+/// Reads a command and the time it was entered from a line of a history file. Lines saved before
+/// times were added hold only the command.
+///
+/// # Arguments
+///
+/// * `record` - The line of the history file
+fn from_timed_record(record: &str) -> (Vec<String>, EntryTime) {
+    match record.split_once('\t') {
+        Some((time, command)) => match DateTime::parse_from_rfc3339(time) {
+            Ok(time) => (from_record(command), Some(time.with_timezone(&Local))),
+            Err(_) => (from_record(record), None),
+        },
+        None => (from_record(record), None),
+    }
+}
+
+/// Formats a command as a line of a history file. Newlines, such as those in a heredoc's body,
+/// are written as `\n` and backslashes as `\\`, so every command takes one line.
+///
+/// # Arguments
+///
+/// * `command` - The command to save
+fn to_record(command: &[String]) -> String {
+    command
+        .iter()
+        .map(|token| token.replace('\\', "\\\\").replace('\n', "\\n"))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Reads a command from a line of a history file, undoing the escapes of `to_record`. A
+/// backslash before any other character is kept, as in files saved before escaping was added.
+///
+/// # Arguments
+///
+/// * `record` - The line of the history file
+fn from_record(record: &str) -> Vec<String> {
+    record
+        .split_whitespace()
+        .map(|token| {
+            let mut unescaped: String = String::new();
+            let mut chars = token.chars().peekable();
+
+            while let Some(c) = chars.next() {
+                match (c, chars.peek()) {
+                    ('\\', Some('n')) => {
+                        chars.next();
+                        unescaped.push('\n');
+                    }
+                    ('\\', Some('\\')) => {
+                        chars.next();
+                        unescaped.push('\\');
+                    }
+                    _ => unescaped.push(c),
+                }
+            }
+            unescaped
+        })
+        .collect()
+}
+
+/// Escapes a string for use inside a JSON string literal
+///
+/// # Arguments
+///
+/// * `text` - The string to escape
+fn json_escape(text: &str) -> String {
+    let mut escaped: String = String::new();
+
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            // Other control characters have no short escape
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Creates a history holding the given lines, entered in this session, without reading any
+    /// history file
+    fn history_of(lines: &[&str]) -> History {
+        let commands: Vec<Rc<[String]>> = lines
+            .iter()
+            .map(|line| line.split_whitespace().map(String::from).collect())
+            .collect();
+
+        History {
+            times: vec![Some(Local::now()); commands.len()],
+            commands,
+            loaded: 0,
+            started: Local::now(),
+            per_directory: false,
+            directory_commands: Vec::new(),
+        }
+    }
+
+    /// Expands a line against a history
+    fn expand(history: &History, line: &str) -> Result<Vec<String>, Error> {
+        let tokens: Rc<[String]> = line.split_whitespace().map(String::from).collect();
+        history.expand(tokens).map(|expanded| expanded.to_vec())
+    }
+
+    #[test]
+    fn bang_bang_recalls_the_last_command() {
+        let history: History = history_of(&["ls -a", "echo hi"]);
+        assert_eq!(expand(&history, "!!").unwrap(), ["echo", "hi"]);
+        assert_eq!(
+            expand(&history, "!! there").unwrap(),
+            ["echo", "hi", "there"]
+        );
+    }
+
+    #[test]
+    fn bang_number_recalls_by_position() {
+        let history: History = history_of(&["ls -a", "echo hi"]);
+        assert_eq!(expand(&history, "!1").unwrap(), ["ls", "-a"]);
+        assert!(expand(&history, "!0").is_err());
+        assert!(expand(&history, "!3").is_err());
+    }
+
+    #[test]
+    fn bang_prefix_recalls_the_latest_match() {
+        let history: History = history_of(&["echo one", "ls", "echo two"]);
+        assert_eq!(expand(&history, "!ec").unwrap(), ["echo", "two"]);
+        assert_eq!(expand(&history, "!echo o").unwrap(), ["echo", "two", "o"]);
+        assert!(expand(&history, "!cd").is_err());
+    }
+
+    #[test]
+    fn lines_without_a_reference_are_unchanged() {
+        let history: History = history_of(&["ls"]);
+        assert_eq!(expand(&history, "! ls").unwrap(), ["!", "ls"]);
+        assert_eq!(expand(&history, "echo !!").unwrap(), ["echo", "!!"]);
+    }
+
+    #[test]
+    fn commands_are_counted_by_name_most_used_first() {
+        let history: History = history_of(&[
+            "ls -a",
+            "cd src",
+            "git status",
+            "ls",
+            "git diff",
+            "cd ..",
+            "ls -l",
+            "",
+            "echo hi",
+        ]);
+
+        // Ties are ordered by name, and empty lines are not counted
+        assert_eq!(
+            history.command_counts(),
+            [("ls", 3), ("cd", 2), ("git", 2), ("echo", 1)]
+        );
+    }
+
+    #[test]
+    fn exported_lines_hold_the_index_command_and_time() {
+        let mut history: History = history_of(&["ls -l", "", "echo \"hi\""]);
+        history.times[0] = None;
+
+        let lines: Vec<String> = history.json_lines();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], r#"{"index":1,"command":"ls -l","time":null}"#);
+
+        // The empty line keeps its number, and the time is an RFC 3339 string
+        let (start, time): (&str, &str) = lines[1].split_once(r#""time":""#).unwrap();
+        assert_eq!(start, r#"{"index":3,"command":"echo \"hi\"","#);
+        let time: &str = time.strip_suffix(r#""}"#).unwrap();
+        assert!(DateTime::parse_from_rfc3339(time).is_ok());
+    }
+
+    #[test]
+    fn json_strings_escape_quotes_backslashes_and_control_characters() {
+        assert_eq!(json_escape(r#"say "hi""#), r#"say \"hi\""#);
+        assert_eq!(json_escape(r"C:\dir"), r"C:\\dir");
+        assert_eq!(json_escape("a\nb\tc\rd"), r"a\nb\tc\rd");
+        assert_eq!(json_escape("bell\u{7}"), r"bell\u0007");
+        assert_eq!(json_escape("plain ü"), "plain ü");
+    }
+
+    #[test]
+    fn records_keep_newlines_and_backslashes() {
+        let command: Vec<String> = vec![
+            "cat".to_string(),
+            "<<".to_string(),
+            "one\nC:\\dir\\n\n".to_string(),
+        ];
+        let record: String = to_record(&command);
+        assert!(!record.contains('\n'));
+        assert_eq!(from_record(&record)[2], command[2]);
+    }
+
+    #[test]
+    fn directory_records_without_a_time_are_read() {
+        let (command, time) = from_timed_record("echo a\\tb");
+        assert_eq!(command, ["echo", "a\\tb"]);
+        assert!(time.is_none());
+
+        let (command, time) = from_timed_record("2024-01-02T03:04:05+00:00\tls -a");
+        assert_eq!(command, ["ls", "-a"]);
+        assert!(time.is_some());
+    }
+
+    #[test]
+    fn earlier_global_and_directory_commands_are_merged_by_time() {
+        let at = |hour: u32| Local.with_ymd_and_hms(2024, 1, 2, hour, 0, 0).single();
+        let command = |line: &str| -> Rc<[String]> { line.split(' ').map(String::from).collect() };
+
+        let history: History = History {
+            commands: vec![
+                command("old"),
+                command("ls"),
+                command("pwd"),
+                command("now"),
+            ],
+            times: vec![None, at(1), at(3), Some(Local::now())],
+            loaded: 3,
+            started: Local::now(),
+            per_directory: true,
+            directory_commands: vec![(command("make"), at(2)), (command("make test"), at(4))],
+        };
+
+        let displayed: Vec<String> = history
+            .displayed_commands()
+            .iter()
+            .map(|(command, _)| command.join(" "))
+            .collect();
+        assert_eq!(displayed, ["old", "ls", "make", "pwd", "make test", "now"]);
+        assert_eq!(history.get(3).unwrap().join(" "), "make");
+    }
 }
@@ -0,0 +1,67 @@
+use std::process::Child;
+
+/// A line running in the background
+struct Job {
+    // Number the job is shown with
+    id: usize,
+    // The processes of the line, in pipeline order
+    processes: Vec<Child>,
+    // The line as it was entered
+    command: String,
+}
+
+pub struct Jobs {
+    // Background jobs that have not been seen to finish yet
+    jobs: Vec<Job>,
+}
+
+impl Default for Jobs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        Jobs { jobs: Vec::new() }
+    }
+
+    /// Keeps the processes of a line started in the background so they can be waited on later
+    ///
+    /// # Arguments
+    ///
+    /// * `processes` - The running processes of the line, in pipeline order
+    /// * `command` - The line as it was entered
+    ///
+    /// # Return value
+    ///
+    /// The number of the new job
+    pub fn add(&mut self, processes: Vec<Child>, command: String) -> usize {
+        // Numbers are reused once every job after them has finished, as in other shells
+        let id: usize = self.jobs.last().map_or(1, |job| job.id + 1);
+
+        self.jobs.push(Job {
+            id,
+            processes,
+            command,
+        });
+        id
+    }
+
+    /// Prints and forgets the jobs whose processes have all finished, without waiting on the
+    /// ones still running
+    pub fn reap(&mut self) {
+        self.jobs.retain_mut(|job| {
+            // A process that cannot be checked is treated as finished so it is not kept forever
+            let finished: bool = job
+                .processes
+                .iter_mut()
+                .all(|process| !matches!(process.try_wait(), Ok(None)));
+
+            if finished {
+                println!("[{}] Done    {}", job.id, job.command);
+            }
+            !finished
+        });
+    }
+}
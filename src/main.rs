@@ -1,92 +1,411 @@
+pub mod bookmarks;
 pub mod builtin;
+pub mod checksum;
 pub mod config;
+pub mod context;
+pub mod dir_stack;
+pub mod frecency;
 pub mod history;
+pub mod jobs;
+pub mod options;
 pub mod parser;
+pub mod path_cache;
 pub mod redirect;
+pub mod sys;
+#[cfg(test)]
+mod test_support;
+pub mod traps;
+pub mod undo;
 pub mod utils;
 
 use crate::builtin::builtin;
 use crate::config::Config;
-use crate::history::History;
-use crate::utils::{execute, parse_line, prompt_and_read};
+use crate::context::ShellContext;
+use crate::parser::parse;
+use crate::utils::{
+    abandon_pipeline, execute, execute_in_background, expand_globs, expand_tilde, expand_variables,
+    interrupt_foreground, last_status, parse_line, print_error, print_prompt, prompt_and_read,
+    set_last_status,
+};
 use ctrlc::set_handler;
+use std::env::args;
+use std::io::{stdin, Error, IsTerminal};
+use std::iter::once;
+use std::process::{self, Child};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 /// An implementation of a simple UNIX shell.  This program supports:
 ///    - Running processes
 ///    - Redirecting standard output (>)
 ///    - Redirecting standard input (<)
+///    - Feeding the lines that follow to standard input (<< DELIMITER)
+///    - Feeding a string to standard input (<<< "text")
 ///    - Appending standard output to a file (>>)
 ///    - Redirecting both standard output and standard input (&>)
+///    - Appending both standard output and standard error to a file (&>>)
+///    - Appending standard error to a file (2>>)
+///    - Redirecting standard error to standard output (2>&1)
 ///    - Creating process pipelines (p1 | p2 | ...)
+///    - Backgrounding processes (p1 &)
+///    - Unconditionally chaining processes (p1; p2)
+///    - Conditionally chaining processes (p1 && p2 or p1 || p2)
+///    - Failing a pipeline when any stage fails (set -o pipefail)
+///    - Running commands on Ctrl-C or exit (trap)
 ///    - Interrupting a running process (e.g., ctrl-C)
-///    - A built-in version of the 'ls' command
+///    - Running a command without the shell's status messages (quiet cmd)
+///    - Showing the current directory or running command in the terminal's title
+///    - A built-in version of the 'ls' command, with git statuses and icons
 ///    - A built-in version of the 'rm' command
 ///    - A built-in version of the 'touch' command
 ///    - A built-in version of the 'cd' command
+///    - Bookmarked directories (bookmark, goto, and cd @name)
+///    - A directory stack (pushd, popd, and dirs)
+///    - Jumping to frequently and recently visited directories (z)
 ///    - A built-in version of the 'pwd' command
-///    - A built-in 'history' list
+///    - A built-in version of the 'echo' command
+///    - Replacing the shell with another program (exec)
+///    - Running a program with a lower or higher priority (nice)
+///    - A built-in 'history' list, kept between sessions
+///    - Undoing 'touch' and, with the trash, 'rm' (undo)
+///    - Expanding environment variables ($NAME, ${NAME}) the shell's PID ($$), and the last exit status ($?)
+///    - Expanding the home directory (~ and ~/path)
+///    - Expanding wildcards in filenames (* and ?)
+///    - Re-running history commands (!!, !n, or !prefix)
+///    - A built-in 'hash' table of command locations
+///    - A built-in 'strings' for finding text in binary files
+///    - A built-in 'file' for guessing file types
+///    - Built-in 'md5sum' and 'sha256sum' checksums
+///    - A built-in 'split' for dividing files into pieces
+///    - A built-in 'paste' for joining the lines of files
 ///
 /// Among the many things it does _NOT_ support are:
-///    - Environment variables
-///    - Appending standard error to a file (2>>)
-///    - Backgrounding processes (p1&)
-///    - Unconditionally chaining processes (p1;p2)
-///    - Conditionally chaining processes (p1 && p2 or p1 || p2)
-///    - re-executing history commands
-
+///    - Setting environment variables
 fn main() {
     let mut config: Config = Config::new();
 
-    config.read_config_file();
+    // '--no-rc' or '--norc' starts the shell with the default settings only
+    let skip_startup_files: bool = args()
+        .skip(1)
+        .any(|arg| arg == "--no-rc" || arg == "--norc");
 
-    // History object to track every command entered during the lifetime of the program
-    let mut history: History = History::new();
+    if !skip_startup_files {
+        config.read_config_file();
+    }
+
+    // Whether a user is typing commands at a terminal, rather than them being piped in
+    let interactive: bool = stdin().is_terminal();
+
+    // State kept between commands, including every command entered in earlier sessions
+    let mut context: ShellContext = ShellContext::new(config, interactive);
+
+    if context.config.get("per_directory_history") == "true" {
+        if let Err(e) = context.history.enable_per_directory() {
+            print_error(
+                "Could not load history for this directory",
+                &e,
+                &context.config,
+            );
+        }
+    }
 
-    // Allows program to not be stopped when 'CTRL+C' is entered
-    set_handler(|| eprint!("")).expect("Error setting Ctrl-C handler");
+    // Whether Ctrl-C has been pressed since its trap was last run
+    let interrupted: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+
+    // Allows program to not be stopped when 'CTRL+C' is entered, interrupting the running line
+    // instead, or starting a fresh prompt if nothing is running
+    let handler_interrupted: Arc<AtomicBool> = Arc::clone(&interrupted);
+    set_handler(move || {
+        handler_interrupted.store(true, Ordering::SeqCst);
+
+        if !interrupt_foreground() && interactive {
+            println!();
+            print_prompt();
+        }
+    })
+    .expect("Error setting Ctrl-C handler");
 
     loop {
-        // Entire entered line
-        let tokens: Vec<String> = prompt_and_read().unwrap_or(Vec::new());
-
-        history.add_to_history(&tokens);
-
-        // Check if user want to run a builtin or not
-        if let Ok(false) = builtin(&tokens, &mut history, &config) {
-            // Returned process from parsed line
-            let parsed_command = parse_line(&tokens, None);
-
-            if let Ok(Some(mut child)) = parsed_command {
-                if let Err(e) = execute(&mut child) {
-                    // Stops shell when exit is entered
-                    if &tokens[0] == "exit" {
-                        break;
-                    } else {
-                        eprintln!(
-                            "\x1b[38;2;{}mError: Could not execute process.\n{}\x1b[0m",
-                            &config.get("error_text_color"),
-                            e
-                        );
-                    }
-                }
-            } else {
-                // Reasons this will execute:
-                // * User entered only whitespace
-                // * CTRL+C or CTRL+D was pressed in parent process
-                // * One of the redirect functions was last to return
-                //      ie: cat input.txt << file.txt
-                // * Pipe encountered an error and returned early
-                // * An error occurred parsing the line
-                //      Specifically, writing to stdout/stderr in parse_line
-
-                if let Err(e) = parsed_command {
-                    eprintln!("\x1b[38;2;{}m{}\x1b[0m", &config.get("error_text_color"), e);
+        // Report background jobs that finished while the last line ran
+        context.jobs.reap();
+
+        // Entire entered line; the shell exits once input ends, as 'exit' does
+        let tokens: Rc<[String]> = match prompt_and_read(
+            context.interactive,
+            context.config.get("set_title") == "true",
+            &context.config.get("command_separator"),
+        ) {
+            Some(tokens) => tokens.into(),
+            None => break,
+        };
+
+        // The line with any reference to an earlier command replaced by that command
+        let tokens: Rc<[String]> = match context.history.expand(tokens) {
+            Ok(expanded) => expanded,
+            Err(e) => {
+                print_error("Could not find the history entry", &e, &context.config);
+                continue;
+            }
+        };
+
+        // Failing to save a command should not stop it from running
+        if let Err(e) = context.history.add_to_history(Rc::clone(&tokens)) {
+            print_error(
+                "Could not save history for this directory",
+                &e,
+                &context.config,
+            );
+        }
+
+        // A Ctrl-C at the prompt has already been answered with a fresh prompt, so the INT trap is
+        // only for one that interrupts this line
+        interrupted.store(false, Ordering::SeqCst);
+
+        if !run_line(&tokens, &mut context) {
+            break;
+        }
+
+        // Ctrl-C is only acted on between commands, so its trap runs once the line has finished
+        if interrupted.swap(false, Ordering::SeqCst) {
+            if let Some(command) = context.traps.get("INT").cloned() {
+                let separator: String = context.config.get("command_separator");
+                if !run_line(&parse(command, &separator), &mut context) {
+                    break;
                 }
             }
+        }
+    }
+
+    process::exit(shut_down(&mut context));
+}
+
+/// Runs the EXIT trap, then saves the history, or clears it if the settings ask to
+///
+/// # Arguments
+///
+/// * `context` - The shell's state
+///
+/// # Return value
+///
+/// The status the shell exits with, which is that of the last command before the EXIT trap
+fn shut_down(context: &mut ShellContext) -> i32 {
+    // The EXIT trap does not change the status the shell exits with
+    let exit_status: i32 = last_status();
+
+    if let Some(command) = context.traps.get("EXIT").cloned() {
+        let separator: String = context.config.get("command_separator");
+        run_line(&parse(command, &separator), context);
+    }
+
+    // Leaves no record of the session on shared machines
+    if context.config.get("clear_history_on_exit") == "true" {
+        if let Err(e) = context.history.clear() {
+            print_error("Could not clear history", &e, &context.config);
+        }
+    }
+    // Keeps the history for the next session
+    else if let Err(e) = context.history.save() {
+        print_error("Could not save history", &e, &context.config);
+    }
+
+    exit_status
+}
+
+/// Runs each of the commands of a line in turn. Commands after ';' or '&' always run, those ending
+/// in '&' are run in the background, and those after '&&' or '||' only run if the command before
+/// them succeeded or failed. The 'command_separator' setting adds another token that separates
+/// commands like ';'.
+///
+/// # Arguments
+///
+/// * `tokens` - The line, split into commands, their arguments, redirections, and separators
+/// * `context` - The shell's state, which commands read and change
+///
+/// # Return value
+///
+/// False if the shell should exit, else true
+fn run_line(tokens: &[String], context: &mut ShellContext) -> bool {
+    // Index of the first token of the next command
+    let mut start: usize = 0;
+
+    // Separator before the next command, which decides whether it runs
+    let mut separator: &str = ";";
+
+    // Exit status of the last command that ran
+    let mut status: i32 = 0;
+
+    // Token that separates commands like a ';'
+    let command_separator: String = context.config.get("command_separator");
+
+    // The end of the line separates the last command like a ';'
+    for (index, token) in tokens
+        .iter()
+        .map(String::as_str)
+        .chain(once(command_separator.as_str()))
+        .enumerate()
+    {
+        // A command ending in '&' keeps it so it is run in the background
+        let (command, next_separator): (&[String], &str) = match token {
+            "&&" | "||" => (&tokens[start..index], token),
+            "&" => (&tokens[start..=index], ";"),
+            // ';' is still an operator to the line parser, so it keeps separating commands
+            _ if token == command_separator || token == ";" => (&tokens[start..index], ";"),
+            _ => continue,
+        };
+        start = index + 1;
+
+        // Chains are decided left to right, so a skipped command leaves the status as it was
+        let should_run: bool = match separator {
+            "&&" => status == 0,
+            "||" => status != 0,
+            _ => true,
+        };
+        separator = next_separator;
+
+        // Empty commands, such as between ';;', are skipped
+        if !should_run || command.is_empty() || command == ["&"] {
+            continue;
+        }
+
+        match run_command(command, context) {
+            Some(command_status) => status = command_status,
+            None => return false,
+        }
+        set_last_status(status);
+    }
+    true
+}
+
+/// Runs a command as a builtin, or as a process if it is not one
+///
+/// # Arguments
+///
+/// * `tokens` - The command, its arguments, and redirections
+/// * `context` - The shell's state, which commands read and change
+///
+/// # Return value
+///
+/// The command's exit status, or `None` if the shell should exit
+fn run_command(tokens: &[String], context: &mut ShellContext) -> Option<i32> {
+    // Home directories and variables are expanded when the line runs, so history keeps them as
+    // typed
+    let tokens: Vec<String> = expand_variables(&expand_tilde(tokens));
+
+    // 'cd' matches its own wildcards, so it can pick out directories and list them when there are
+    // several
+    let tokens: &[String] = &if tokens.first().is_some_and(|token| token == "cd") {
+        tokens
+    } else {
+        expand_globs(&tokens)
+    };
+
+    // A line ending in '&' runs in the background
+    let background: bool = tokens.last().is_some_and(|token| token == "&");
+    let tokens: &[String] = if background {
+        &tokens[..tokens.len() - 1]
+    } else {
+        tokens
+    };
+
+    // A command prefixed with 'quiet' runs without the shell's own messages about it
+    let quiet: bool = tokens
+        .first()
+        .is_some_and(|token| matches!(token.as_str(), "quiet" | "--quiet" | "-q"));
+    let tokens: &[String] = if quiet { &tokens[1..] } else { tokens };
+
+    // Check if user want to run a builtin or not
+    match builtin(tokens, context) {
+        Ok(false) => {}
+        // 'exit' has recorded the status to exit with
+        Ok(true) if tokens[0] == "exit" => return None,
+        Ok(true) => return Some(last_status()),
+        // A builtin that failed has already printed why
+        Err(_) => return Some(last_status().max(1)),
+    }
+
+    // Processes started for the earlier stages of a pipeline
+    let mut stages: Vec<Child> = Vec::new();
+
+    // Returned process from parsed line
+    let parsed_command = parse_line(
+        tokens,
+        None,
+        &mut stages,
+        &mut context.path_cache,
+        background,
+    );
+
+    if let Ok(Some(mut child)) = parsed_command {
+        // Result of starting the line; a background line has succeeded once it has started
+        let result: Result<i32, Error> = if background {
+            execute_in_background(&mut child, stages, &mut context.jobs, &tokens.join(" "))
+                .map(|_| 0)
         } else {
-            // Reasons this will execute:
-            // * A builtin function returned an error
-            // * A builtin function returned true
+            execute(
+                &mut child,
+                stages,
+                &context.options,
+                quiet,
+                context.config.get("set_title") == "true",
+            )
+        };
+
+        match result {
+            Ok(status) => Some(status),
+            Err(e) => {
+                if !quiet {
+                    print_error("Could not execute process", &e, &context.config);
+                }
+                Some(1)
+            }
         }
+    } else {
+        // Reasons this will execute:
+        // * User entered only whitespace
+        // * CTRL+C or CTRL+D was pressed in parent process
+        // * One of the redirect functions was last to return
+        //      ie: cat input.txt << file.txt
+        // * Pipe encountered an error and returned early
+        // * An error occurred parsing the line
+        //      Specifically, writing to stdout/stderr in parse_line
+
+        // Stages started before the error still need to be waited for
+        abandon_pipeline(stages);
+
+        match parsed_command {
+            Err(e) => {
+                if !quiet {
+                    print_error("Could not run the line", &e, &context.config);
+                }
+                Some(1)
+            }
+            Ok(_) => Some(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_directory, EnvGuard};
+    use std::path::PathBuf;
+
+    #[test]
+    fn exit_trap_runs_on_shutdown() {
+        let mut env: EnvGuard = EnvGuard::lock();
+        let home: PathBuf = temp_directory("exit-trap");
+        env.set("HOME", &home);
+
+        let mut context: ShellContext = ShellContext::new(Config::new(), false);
+        let marker: PathBuf = home.join("trapped");
+        let command: String = format!("touch {}", marker.display());
+        context.traps.set("EXIT", &command).unwrap();
+        set_last_status(3);
+
+        // The trap runs without changing the status the shell exits with
+        assert_eq!(shut_down(&mut context), 3);
+        assert!(marker.exists());
+        assert!(home.join(".gecko_history").exists());
     }
 }
@@ -0,0 +1,80 @@
+use std::io::{Error, ErrorKind};
+
+/// Names of every option, in the order they are listed
+const OPTION_NAMES: [&str; 1] = ["pipefail"];
+
+pub struct ShellOptions {
+    // A pipeline fails if any of its stages fail, not only the last one
+    pipefail: bool,
+}
+
+impl Default for ShellOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ShellOptions {
+    /// Create options object with every option turned off
+    pub const fn new() -> Self {
+        ShellOptions { pipefail: false }
+    }
+
+    /// Turns the named option on or off
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the option, as given to `set -o`
+    /// * `value` - Whether the option should be on
+    pub fn set(&mut self, name: &str, value: bool) -> Result<(), Error> {
+        match name {
+            "pipefail" => self.pipefail = value,
+            _ => {
+                let error_message: String = name.to_owned() + ": invalid option name";
+                return Err(Error::new(ErrorKind::InvalidInput, error_message));
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets whether the named option is on
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the option, as given to `set -o`
+    pub fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "pipefail" => Some(self.pipefail),
+            _ => None,
+        }
+    }
+
+    /// Prints every option and whether it is on
+    pub fn display(&self) {
+        for name in OPTION_NAMES.iter() {
+            let state: &str = if self.get(name) == Some(true) {
+                "on"
+            } else {
+                "off"
+            };
+            println!("{:<15} {}", name, state);
+        }
+    }
+
+    /// Prints the 'set' commands that would recreate the current options
+    pub fn display_commands(&self) {
+        for name in OPTION_NAMES.iter() {
+            let flag: &str = if self.get(name) == Some(true) {
+                "-o"
+            } else {
+                "+o"
+            };
+            println!("set {} {}", flag, name);
+        }
+    }
+
+    /// Whether a pipeline's status is that of its rightmost failed stage
+    pub fn pipefail(&self) -> bool {
+        self.pipefail
+    }
+}
@@ -7,7 +7,15 @@ use pest_derive::Parser;
 #[grammar = "grammar.pest"]
 pub struct ArgParser;
 
-pub fn parse(to_parse: String) -> Vec<String> {
+/// Splits a line into tokens. Text in quotes is one token, and the 'command_separator' setting is
+/// split off the text around it as its own token, unless it is made of letters or digits and would
+/// otherwise split words.
+///
+/// # Arguments
+///
+/// * `to_parse` - The line, with its newline
+/// * `separator` - The token that separates commands like ';'
+pub fn parse(to_parse: String, separator: &str) -> Vec<String> {
     // Parsing the input string via the `line` rule in grammar.pest
     let parsed = ArgParser::parse(Rule::line, &to_parse)
         .expect("Failed to parse")
@@ -25,12 +33,24 @@ pub fn parse(to_parse: String) -> Vec<String> {
                 // Trim the quotation marks off of the string
                 let token = line.as_str();
                 let token = &token[1..token.len() - 1];
-                tokens.push(token.to_string());
+                // Wildcards in quotes are escaped so they are not expanded
+                tokens.push(token.replace('*', "\\*").replace('?', "\\?"));
             }
             Rule::commands => {
                 // Get each `command` present
                 for command in line.into_inner() {
-                    tokens.push(command.as_str().to_string());
+                    let is_file: bool = command
+                        .clone()
+                        .into_inner()
+                        .next()
+                        .is_some_and(|inner| inner.as_rule() == Rule::file);
+
+                    // Operators and words never hold the separator
+                    if is_file {
+                        push_split(command.as_str(), separator, &mut tokens);
+                    } else {
+                        tokens.push(command.as_str().to_string());
+                    }
                 }
             }
             _ => {
@@ -41,3 +61,74 @@ pub fn parse(to_parse: String) -> Vec<String> {
 
     tokens
 }
+
+/// Adds a token to the line, with each command separator in it as a token of its own
+///
+/// # Arguments
+///
+/// * `token` - The token to add
+/// * `separator` - The token that separates commands like ';'
+/// * `tokens` - The tokens of the line so far
+fn push_split(token: &str, separator: &str, tokens: &mut Vec<String>) {
+    // A separator such as 'then' is only one when it is a whole word
+    if separator.is_empty() || separator.chars().any(char::is_alphanumeric) {
+        tokens.push(token.to_string());
+        return;
+    }
+
+    for (index, piece) in token.split(separator).enumerate() {
+        if index > 0 {
+            tokens.push(separator.to_string());
+        }
+        if !piece.is_empty() {
+            tokens.push(piece.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a line as it is read, with its newline
+    fn tokens(line: &str) -> Vec<String> {
+        parse(line.to_string() + "\n", ";")
+    }
+
+    #[test]
+    fn words_are_split_on_whitespace() {
+        assert_eq!(tokens("ls -a  src/"), ["ls", "-a", "src/"]);
+    }
+
+    #[test]
+    fn operators_are_their_own_tokens() {
+        assert_eq!(
+            tokens("cat < in 2>&1 >> out | wc -l"),
+            ["cat", "<", "in", "2>&1", ">>", "out", "|", "wc", "-l"]
+        );
+        assert_eq!(
+            tokens("a && b || c ; d &"),
+            ["a", "&&", "b", "||", "c", ";", "d", "&"]
+        );
+    }
+
+    #[test]
+    fn quoted_text_is_one_token_with_wildcards_escaped() {
+        assert_eq!(tokens("echo \"a b\" \"*.rs\""), ["echo", "a b", "\\*.rs"]);
+    }
+
+    #[test]
+    fn the_command_separator_is_split_out_of_words() {
+        assert_eq!(
+            parse(String::from("echo a,b ,c\n"), ","),
+            ["echo", "a", ",", "b", ",", "c"]
+        );
+        assert_eq!(parse(String::from("echo \"a,b\"\n"), ","), ["echo", "a,b"]);
+
+        // A separator made of letters would split words, so it must stand alone
+        assert_eq!(
+            parse(String::from("echo authentic then ls\n"), "then"),
+            ["echo", "authentic", "then", "ls"]
+        );
+    }
+}
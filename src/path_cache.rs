@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::env;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+
+/// A command location found on PATH
+struct CachedCommand {
+    location: PathBuf,
+    // Number of times the command has been run from this location
+    hits: usize,
+}
+
+pub struct PathCache {
+    // Value of PATH that the cached locations were found with
+    path_var: String,
+    // Command names mapped to their location on PATH
+    locations: HashMap<String, CachedCommand>,
+}
+
+impl Default for PathCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PathCache {
+    pub fn new() -> Self {
+        PathCache {
+            path_var: env::var("PATH").unwrap_or_default(),
+            locations: HashMap::new(),
+        }
+    }
+
+    /// Finds the location of a command on PATH, searching the filesystem only when the command
+    /// has not been found before
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the command to find
+    ///
+    /// # Return value
+    ///
+    /// The full path of the command, or `None` if it is given as a path or is not on PATH
+    pub fn resolve(&mut self, name: &str) -> Option<PathBuf> {
+        // Commands given as a path are run as-is
+        if name.contains('/') {
+            return None;
+        }
+
+        self.check_path_changed();
+
+        if !self.locations.contains_key(name) {
+            self.lookup(name)?;
+        }
+
+        let cached: &mut CachedCommand = self.locations.get_mut(name)?;
+        cached.hits += 1;
+
+        Some(cached.location.clone())
+    }
+
+    /// Checks whether a command is on PATH, without counting it as run
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the command to find
+    pub fn is_on_path(&mut self, name: &str) -> bool {
+        if name.contains('/') {
+            return false;
+        }
+
+        self.check_path_changed();
+        self.locations.contains_key(name) || self.lookup(name).is_some()
+    }
+
+    /// Searches PATH for a command and caches its location, replacing any cached location
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the command to find
+    ///
+    /// # Return value
+    ///
+    /// The full path of the command, or `None` if it is not on PATH
+    pub fn lookup(&mut self, name: &str) -> Option<PathBuf> {
+        self.check_path_changed();
+
+        let location: PathBuf = search_path(&self.path_var, name)?;
+        self.locations.insert(
+            name.to_string(),
+            CachedCommand {
+                location: location.clone(),
+                hits: 0,
+            },
+        );
+
+        Some(location)
+    }
+
+    /// Removes every cached location
+    pub fn clear(&mut self) {
+        self.locations.clear();
+    }
+
+    /// Prints every cached command's location and the number of times it has been run
+    pub fn display(&self) {
+        if self.locations.is_empty() {
+            println!("hash: hash table empty");
+            return;
+        }
+
+        // Sort by name so the output is stable
+        let mut names: Vec<&String> = self.locations.keys().collect();
+        names.sort();
+
+        println!("hits  command");
+        for name in names {
+            let cached: &CachedCommand = &self.locations[name];
+            println!("{:>4}  {}", cached.hits, cached.location.display());
+        }
+    }
+
+    /// Empties the cache if PATH has changed since the cached locations were found
+    fn check_path_changed(&mut self) {
+        let path_var: String = env::var("PATH").unwrap_or_default();
+
+        if path_var != self.path_var {
+            self.path_var = path_var;
+            self.clear();
+        }
+    }
+}
+
+/// Searches each directory of PATH in order for an executable file with the given name
+///
+/// # Arguments
+///
+/// * `path_var` - The value of PATH to search
+/// * `name` - The name of the command to find
+fn search_path(path_var: &str, name: &str) -> Option<PathBuf> {
+    env::split_paths(path_var)
+        .map(|directory| directory.join(name))
+        .find(|candidate| {
+            candidate
+                .metadata()
+                .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+                .unwrap_or(false)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{command_directory, EnvGuard};
+    use std::fs::remove_dir_all;
+
+    #[test]
+    fn resolved_commands_are_cached() {
+        let mut env: EnvGuard = EnvGuard::lock();
+        let directory: PathBuf = command_directory("hit", &["tool"]);
+        env.set("PATH", &directory);
+
+        let mut cache: PathCache = PathCache::new();
+        assert_eq!(cache.resolve("tool"), Some(directory.join("tool")));
+
+        // A cached location is used without searching PATH again
+        remove_dir_all(&directory).unwrap();
+        assert_eq!(cache.resolve("tool"), Some(directory.join("tool")));
+        assert_eq!(cache.locations["tool"].hits, 2);
+    }
+
+    #[test]
+    fn missing_commands_and_paths_are_not_resolved() {
+        let mut env: EnvGuard = EnvGuard::lock();
+        let directory: PathBuf = command_directory("miss", &["tool"]);
+        env.set("PATH", &directory);
+
+        let mut cache: PathCache = PathCache::new();
+        assert_eq!(cache.resolve("missing"), None);
+        assert_eq!(cache.resolve("./tool"), None);
+        assert!(cache.locations.is_empty());
+
+        remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn cache_is_emptied_when_path_changes() {
+        let mut env: EnvGuard = EnvGuard::lock();
+        let first: PathBuf = command_directory("first", &["tool"]);
+        let second: PathBuf = command_directory("second", &["tool"]);
+        env.set("PATH", &first);
+
+        let mut cache: PathCache = PathCache::new();
+        assert_eq!(cache.resolve("tool"), Some(first.join("tool")));
+
+        env.set("PATH", &second);
+        assert_eq!(cache.resolve("tool"), Some(second.join("tool")));
+        assert_eq!(cache.locations["tool"].hits, 1);
+
+        remove_dir_all(&first).unwrap();
+        remove_dir_all(&second).unwrap();
+    }
+}
@@ -1,6 +1,38 @@
+use crate::path_cache::PathCache;
 use std::fs::{File, OpenOptions};
-use std::io::{self, Error, ErrorKind, Write};
+use std::io::{self, Error, ErrorKind, PipeReader, PipeWriter, Write};
+use std::os::fd::AsFd;
+use std::os::unix::process::CommandExt;
 use std::process::{Child, Command, Output, Stdio};
+use std::thread;
+
+/// Where standard output of the process being set up points, which '2>&1' copies to standard
+/// error. Redirections are applied left to right, so only those before '2>&1' count.
+#[derive(Default)]
+pub struct OutputState {
+    // The file standard output was last redirected to, if any
+    stdout_file: Option<File>,
+    // Whether '2>&1' came before any redirection of standard output, so standard error goes where
+    // standard output first pointed: the pipe to the next stage, or else the shell's stdout
+    stderr_follows_stdout: bool,
+    // Whether the line runs in the background, so its stages are started in their own process
+    // group
+    background: bool,
+}
+
+impl OutputState {
+    /// Creates the state of a line's first process, whose output is not yet redirected
+    ///
+    /// # Arguments
+    ///
+    /// * `background` - Whether the line runs in the background
+    pub fn new(background: bool) -> Self {
+        OutputState {
+            background,
+            ..OutputState::default()
+        }
+    }
+}
 
 /// Handles redirection
 ///
@@ -9,7 +41,10 @@ use std::process::{Child, Command, Output, Stdio};
 /// * `redirector` - A string representing the redirect operation to perform, if any
 /// * `command` - A slice of strings representing a command and its arguments
 /// * `process` - An `Option` representing a read-to-execute Command to be
-///               modified/executed/returned
+///   modified/executed/returned
+/// * `stages` - The pipeline stages that have already been started
+/// * `path_cache` - Cached locations of commands on PATH
+/// * `output` - Where standard output of the process being set up points
 ///
 /// # Return value
 ///
@@ -18,33 +53,111 @@ pub fn redirect(
     redirector: &str,
     command: &[String],
     process: Option<Command>,
+    stages: &mut Vec<Child>,
+    path_cache: &mut PathCache,
+    output: &mut OutputState,
 ) -> Result<Option<Command>, Error> {
+    // A later redirection of standard error replaces an earlier '2>&1'
+    if matches!(redirector, "2>" | "2>>" | "&>" | "&>>") {
+        output.stderr_follows_stdout = false;
+    }
+
     match redirector {
         // ---- Append redirection ----
-        ">>" => handle_append_redirect(command, process),
+        ">>" => handle_append_redirect(command, process, output),
 
         // ---- stderr redirection ----
         "2>" => handle_stderr_redirect(command, process),
 
+        // ---- stderr append redirection ----
+        "2>>" => handle_append_stderr_redirect(command, process),
+
+        // ---- stderr to wherever stdout points ----
+        "2>&1" => handle_stderr_to_stdout_redirect(command, process, output),
+
         // ---- stdout and stderr redirection ----
-        "&>" => handle_stdout_stderr_redirect(command, process),
+        "&>" => handle_stdout_stderr_redirect(command, process, output),
+
+        // ---- stdout and stderr append redirection ----
+        "&>>" => handle_append_stdout_stderr_redirect(command, process, output),
 
         // ---- Stdout redirection ----
-        ">" | "1>" => handle_stdout_redirect(command, process),
+        ">" | "1>" => handle_stdout_redirect(command, process, output),
 
         // ---- Stdin redirection ----
         "<" => handle_stdin_redirect(command, process),
 
+        // ---- Heredoc, whose body was read in place of its delimiter ----
+        "<<" => handle_heredoc_redirect(command, process),
+
+        // ---- Here-string ----
+        "<<<" => handle_here_string_redirect(command, process),
+
         // ---- pipe in between processes ----
-        "|" => handle_pipe(command, process),
+        "|" => handle_pipe(command, process, stages, path_cache, output),
+
+        // ---- Backgrounding, which is only allowed at the end of a line ----
+        "&" => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: <command> [args] &",
+        )),
         _ => {
-            let mut setup_command: Command = Command::new(&command[0]);
+            let mut setup_command: Command = new_command(&command[0], path_cache);
             setup_command.args(&command[1..command.len()]);
             Ok(Option::from(setup_command))
         }
     }
 }
 
+/// Applies a '2>&1' that came before any redirection of standard output, once the whole line has
+/// been parsed without a pipe taking the output
+///
+/// # Arguments
+///
+/// * `process` - The ready-to-execute Command, if any
+/// * `output` - Where standard output of the process points
+///
+/// # Return Value
+///
+/// A `Result` with an `Option` containing a ready-to-execute `Command`
+pub fn finish_redirects(
+    process: Option<Command>,
+    output: &mut OutputState,
+) -> Result<Option<Command>, Error> {
+    match process {
+        Some(mut command) if output.stderr_follows_stdout => {
+            // Standard error shares the shell's stdout, as the process's stdout does
+            command.stderr(Stdio::from(io::stdout().as_fd().try_clone_to_owned()?));
+            output.stderr_follows_stdout = false;
+            Ok(Some(command))
+        }
+        process => Ok(process),
+    }
+}
+
+/// Creates a Command for the given program, running it from its cached PATH location when found
+///
+/// # Arguments
+///
+/// * `program` - The name or path of the program to run
+/// * `path_cache` - Cached locations of commands on PATH
+///
+/// # Return value
+///
+/// A `Command` with no arguments
+pub fn new_command(program: &str, path_cache: &mut PathCache) -> Command {
+    match path_cache.resolve(program) {
+        Some(location) => {
+            let mut command: Command = Command::new(location);
+
+            // Keep the name that was typed as the program's argv[0]
+            command.arg0(program);
+            command
+        }
+        None => Command::new(program),
+    }
+}
+
 /// Redirects standard output from this ready-to-execute Command to the file with the specified
 /// name.
 /// Data is appended to the file instead of truncating existing file.
@@ -60,12 +173,14 @@ pub fn redirect(
 fn handle_append_redirect(
     tokens: &[String],
     process: Option<Command>,
+    output: &mut OutputState,
 ) -> Result<Option<Command>, Error> {
     // File to append to
     let stdout_file: File = OpenOptions::new()
         .create(true)
         .append(true)
         .open(&tokens[0])?;
+    output.stdout_file = Some(stdout_file.try_clone()?);
 
     // Received process with stdout redirected
     let mut process_redirected: Command = process.unwrap();
@@ -117,6 +232,81 @@ fn handle_stderr_redirect(
     Ok(Option::from(command))
 }
 
+/// Redirects standard error from this ready-to-execute Command to the file with the specified name.
+/// Data is appended to the file instead of truncating existing file.
+///
+/// # Arguments
+///
+/// * `tokens` - A vector of strings corresponding to the command/operator and its arguments
+/// * `process` - The current ready-to-execute Command to be redirected
+///
+/// # Return Value
+///
+/// A `Result` with an `Option` containing a ready-to-execute `Command`
+fn handle_append_stderr_redirect(
+    tokens: &[String],
+    process: Option<Command>,
+) -> Result<Option<Command>, Error> {
+    //check that a file for redirect was provided
+    if tokens.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: <command> [args] 2>> <file>",
+        ));
+    }
+
+    // File to append stderr to
+    let stderr_file: File = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&tokens[0])?;
+
+    // Received process with stderr redirected
+    let mut process_redirected: Command = process.unwrap();
+    process_redirected.stderr(stderr_file);
+
+    Ok(Option::from(process_redirected))
+}
+
+/// Redirects standard error from this ready-to-execute Command to wherever its standard output
+/// points. If standard output has not been redirected yet, standard error follows where it first
+/// points, which is only known once the pipe to the next stage, if any, is set up.
+///
+/// # Arguments
+///
+/// * `tokens` - A vector of strings corresponding to the command/operator and its arguments
+/// * `process` - The current ready-to-execute Command to be redirected
+/// * `output` - Where standard output of the process points
+///
+/// # Return Value
+///
+/// A `Result` with an `Option` containing a ready-to-execute `Command`
+fn handle_stderr_to_stdout_redirect(
+    tokens: &[String],
+    process: Option<Command>,
+    output: &mut OutputState,
+) -> Result<Option<Command>, Error> {
+    //check that nothing but the operator was given
+    if !tokens.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: <command> [args] 2>&1",
+        ));
+    }
+
+    let mut command: Command = process.unwrap();
+
+    match &output.stdout_file {
+        // A second handle to the file shares its offset, so the streams do not overwrite each other
+        Some(stdout_file) => {
+            command.stderr(Stdio::from(stdout_file.try_clone()?));
+        }
+        None => output.stderr_follows_stdout = true,
+    }
+
+    Ok(Option::from(command))
+}
+
 /// Redirects stdout and stderr from this ready-to-execute Command to the file with the specified
 /// name.
 ///
@@ -131,6 +321,7 @@ fn handle_stderr_redirect(
 fn handle_stdout_stderr_redirect(
     tokens: &[String],
     process: Option<Command>,
+    output: &mut OutputState,
 ) -> Result<Option<Command>, Error> {
     // File that stdout will print to
     let stdout_file: File = OpenOptions::new()
@@ -138,6 +329,7 @@ fn handle_stdout_stderr_redirect(
         .create(true)
         .truncate(true)
         .open(&tokens[0])?;
+    output.stdout_file = Some(stdout_file.try_clone()?);
 
     // File that stderr will print to
     let stderr_file: File = OpenOptions::new()
@@ -155,6 +347,50 @@ fn handle_stdout_stderr_redirect(
     Ok(Option::from(command))
 }
 
+/// Redirects stdout and stderr from this ready-to-execute Command to the file with the specified
+/// name.
+/// Data is appended to the file instead of truncating existing file.
+///
+/// # Arguments
+///
+/// * `tokens` - A vector of strings corresponding to the command/operator and its arguments
+/// * `process` - The current ready-to-execute Command to be redirected
+///
+/// # Return Value
+///
+/// A `Result` with an `Option` containing a ready-to-execute `Command`
+fn handle_append_stdout_stderr_redirect(
+    tokens: &[String],
+    process: Option<Command>,
+    output: &mut OutputState,
+) -> Result<Option<Command>, Error> {
+    //check that a file for redirect was provided
+    if tokens.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: <command> [args] &>> <file>",
+        ));
+    }
+
+    // File that stdout will append to
+    let stdout_file: File = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&tokens[0])?;
+
+    // Handle to the same file for stderr so both streams share one append offset
+    let stderr_file: File = stdout_file.try_clone()?;
+    output.stdout_file = Some(stdout_file.try_clone()?);
+
+    // New edited command
+    let mut command: Command = process.unwrap();
+    command
+        .stdout(Stdio::from(stdout_file))
+        .stderr(Stdio::from(stderr_file));
+
+    Ok(Option::from(command))
+}
+
 /// Redirects standard output from this ready-to-execute Command to a file with the specified name.
 ///
 /// # Arguments
@@ -168,6 +404,7 @@ fn handle_stdout_stderr_redirect(
 fn handle_stdout_redirect(
     tokens: &[String],
     process: Option<Command>,
+    output: &mut OutputState,
 ) -> Result<Option<Command>, Error> {
     // File to write stdout to
     let stdout_file: File = OpenOptions::new()
@@ -175,6 +412,7 @@ fn handle_stdout_redirect(
         .write(true)
         .truncate(true)
         .open(&tokens[0])?;
+    output.stdout_file = Some(stdout_file.try_clone()?);
 
     // Process with its stdout redirected to a file
     let mut process_redirected: Command = process.unwrap();
@@ -223,21 +461,115 @@ fn handle_stdin_redirect(
     Ok(Option::from(command))
 }
 
+/// Redirects standard input of this ready-to-execute Command to the body of a heredoc
+///
+/// # Arguments
+///
+/// * `tokens` - A slice holding the body of the heredoc
+/// * `process` - An `Option` representing a ready-to-execute Command to redirect
+///
+/// # Return Value
+///
+/// A `Result` with an `Option` containing a ready-to-execute `Command`
+fn handle_heredoc_redirect(
+    tokens: &[String],
+    process: Option<Command>,
+) -> Result<Option<Command>, Error> {
+    //check that a delimiter was provided, and so a body read
+    if tokens.len() != 1 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: <command> [args] << <delimiter>",
+        ));
+    }
+
+    let mut command: Command = process.ok_or(Error::new(
+        ErrorKind::InvalidInput,
+        "Usage: <command> [args] << <delimiter>",
+    ))?;
+
+    feed_stdin(&mut command, tokens[0].clone())?;
+
+    Ok(Option::from(command))
+}
+
+/// Redirects standard input of this ready-to-execute Command to a string, followed by a newline
+///
+/// # Arguments
+///
+/// * `tokens` - A slice holding the string
+/// * `process` - An `Option` representing a ready-to-execute Command to redirect
+///
+/// # Return Value
+///
+/// A `Result` with an `Option` containing a ready-to-execute `Command`
+fn handle_here_string_redirect(
+    tokens: &[String],
+    process: Option<Command>,
+) -> Result<Option<Command>, Error> {
+    //check that exactly one string was provided; one with spaces must be quoted
+    if tokens.len() != 1 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Usage: <command> [args] <<< <string>",
+        ));
+    }
+
+    let mut command: Command = process.ok_or(Error::new(
+        ErrorKind::InvalidInput,
+        "Usage: <command> [args] <<< <string>",
+    ))?;
+
+    feed_stdin(&mut command, tokens[0].clone() + "\n")?;
+
+    Ok(Option::from(command))
+}
+
+/// Sets standard input of a Command to a pipe holding the given text
+///
+/// # Arguments
+///
+/// * `command` - The ready-to-execute Command to read the text
+/// * `text` - The text to read
+fn feed_stdin(command: &mut Command, text: String) -> Result<(), Error> {
+    let (reader, mut writer): (PipeReader, PipeWriter) = io::pipe()?;
+
+    // The text is written from another thread, so text larger than the pipe can hold does not
+    // block the shell before the process is started to read it
+    thread::spawn(move || {
+        // The process may exit without reading all of it
+        let _ = writer.write_all(text.as_bytes());
+    });
+
+    command.stdin(Stdio::from(reader));
+    Ok(())
+}
+
 /// Partial implementation of a pipe between two processes.
 ///
 /// # Arguments
 ///
 /// * `commands` - A vector of strings corresponding to a command/operator and its arguments
 /// * `process` - A ready to run Command whose output should be set up to be piped into a new
-///               ready-to-run-command
-///    process is the the left hand side process in a `LHS process | RHS process`
+///   ready-to-run-command
+///   process is the the left hand side process in a `LHS process | RHS process`
+/// * `stages` - The pipeline stages that have already been started, which the spawned LHS
+///   process is added to
+/// * `path_cache` - Cached locations of commands on PATH
+/// * `output` - Where standard output of the LHS process points, which is reset for the RHS
 ///
 /// # Return value
 ///
 /// A `Result` with an `Option` containing a ready-to-execute `Command`
-fn handle_pipe(commands: &[String], process: Option<Command>) -> Result<Option<Command>, Error> {
+fn handle_pipe(
+    commands: &[String],
+    process: Option<Command>,
+    stages: &mut Vec<Child>,
+    path_cache: &mut PathCache,
+    output: &mut OutputState,
+) -> Result<Option<Command>, Error> {
     // If RHS of pipe is empty
-    if commands.len() == 0 {
+    if commands.is_empty() {
         return Err(Error::new(
             ErrorKind::InvalidInput,
             "Usage: <command> | <command>",
@@ -245,7 +577,7 @@ fn handle_pipe(commands: &[String], process: Option<Command>) -> Result<Option<C
     }
 
     // Create the RHS command
-    let mut setup_command: Command = Command::new(&commands[0]);
+    let mut setup_command: Command = new_command(&commands[0], path_cache);
 
     // If the RHS command has arguments add them
     if commands.len() > 1 {
@@ -253,11 +585,87 @@ fn handle_pipe(commands: &[String], process: Option<Command>) -> Result<Option<C
         setup_command.args(command_args);
     }
 
-    // Get the output of the LHS command
-    let process_output: Child = process.unwrap().stdout(Stdio::piped()).spawn()?;
+    let mut lhs_command: Command = process.unwrap();
+
+    if output.background {
+        lhs_command.process_group(background_process_group(stages));
+    }
+
+    if output.stderr_follows_stdout {
+        // After '2>&1', both streams of the LHS command go into the pipe
+        let (reader, writer): (PipeReader, PipeWriter) = io::pipe()?;
+        lhs_command.stdout(writer.try_clone()?).stderr(writer);
+
+        let process_output: Child = lhs_command.spawn()?;
 
-    // Pipe the output of the LHS command to the RHS command
-    setup_command.stdin(process_output.stdout.unwrap());
+        // The command holds the write end of the pipe, which has to be closed for the RHS command
+        // to see the end of its input
+        drop(lhs_command);
+
+        setup_command.stdin(reader);
+        stages.push(process_output);
+    } else {
+        // Get the output of the LHS command
+        let mut process_output: Child = lhs_command.stdout(Stdio::piped()).spawn()?;
+
+        // Pipe the output of the LHS command to the RHS command
+        setup_command.stdin(process_output.stdout.take().unwrap());
+
+        // Keep the LHS process so its exit status can be collected
+        stages.push(process_output);
+    }
+
+    // The RHS command starts with its output unredirected
+    *output = OutputState::new(output.background);
 
     Ok(Option::from(setup_command))
 }
+
+/// Gets the process group to start the next stage of a background line in. The stages share a
+/// group apart from the shell's, so Ctrl-C at the terminal does not reach them.
+///
+/// # Arguments
+///
+/// * `stages` - The already running processes of the line, in pipeline order
+///
+/// # Return value
+///
+/// The PID of the first stage, which leads the group, or 0 to start a new group led by the next
+/// stage
+pub fn background_process_group(stages: &[Child]) -> i32 {
+    stages.first().map_or(0, |stage| stage.id() as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_directory, EnvGuard};
+    use crate::utils::parse_line;
+    use std::fs::read_to_string;
+    use std::path::PathBuf;
+
+    #[test]
+    fn append_both_streams_accumulates_over_runs() {
+        let _env: EnvGuard = EnvGuard::lock();
+        let out: PathBuf = temp_directory("append-both").join("out");
+
+        for _ in 0..2 {
+            let tokens: Vec<String> = vec![
+                String::from("sh"),
+                String::from("-c"),
+                String::from("echo out; echo err >&2"),
+                String::from("&>>"),
+                out.display().to_string(),
+            ];
+            let mut stages: Vec<Child> = Vec::new();
+            let mut process: Command =
+                parse_line(&tokens, None, &mut stages, &mut PathCache::new(), false)
+                    .unwrap()
+                    .unwrap();
+            assert!(process.status().unwrap().success());
+        }
+
+        // Both streams share one offset, so neither overwrites the other
+        assert_eq!(read_to_string(&out).unwrap(), "out\nerr\nout\nerr\n");
+    }
+}
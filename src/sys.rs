@@ -0,0 +1,173 @@
+use std::env;
+use std::ffi::CStr;
+use std::io::Error;
+use std::mem::MaybeUninit;
+use std::os::unix::io::RawFd;
+use std::ptr;
+
+/// Looks up the name of a user
+///
+/// # Arguments
+///
+/// * `uid` - The id of the user
+///
+/// # Return value
+///
+/// The user's name, or `None` if the id has no entry in the user database
+pub fn user_name(uid: u32) -> Option<String> {
+    let mut entry: MaybeUninit<libc::passwd> = MaybeUninit::uninit();
+    let mut result: *mut libc::passwd = ptr::null_mut();
+    let mut buffer: Vec<libc::c_char> = vec![0; 4096];
+
+    // Safety: every pointer refers to memory that outlives the call, and the name is only read
+    // when the lookup reports it filled in `entry`
+    unsafe {
+        libc::getpwuid_r(
+            uid,
+            entry.as_mut_ptr(),
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        );
+
+        if result.is_null() {
+            return None;
+        }
+        Some(
+            CStr::from_ptr((*result).pw_name)
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+/// Finds the name of the user running the shell, preferring $USER when it is set
+pub fn current_user_name() -> Option<String> {
+    env::var("USER")
+        .ok()
+        .filter(|user| !user.is_empty())
+        .or_else(|| {
+            // Safety: geteuid has no preconditions and cannot fail
+            user_name(unsafe { libc::geteuid() })
+        })
+}
+
+/// Finds the name of the machine the shell is running on
+pub fn host_name() -> Result<String, Error> {
+    let mut buffer: Vec<u8> = vec![0; 256];
+
+    // Safety: the length passed is the buffer's own length
+    if unsafe { libc::gethostname(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) } != 0 {
+        return Err(Error::last_os_error());
+    }
+
+    // The name is null terminated within the buffer
+    let length: usize = buffer
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(buffer.len());
+    buffer.truncate(length);
+
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+/// Looks up the name of a group
+///
+/// # Arguments
+///
+/// * `gid` - The id of the group
+///
+/// # Return value
+///
+/// The group's name, or `None` if the id has no entry in the group database
+pub fn group_name(gid: u32) -> Option<String> {
+    let mut entry: MaybeUninit<libc::group> = MaybeUninit::uninit();
+    let mut result: *mut libc::group = ptr::null_mut();
+    let mut buffer: Vec<libc::c_char> = vec![0; 4096];
+
+    // Safety: every pointer refers to memory that outlives the call, and the name is only read
+    // when the lookup reports it filled in `entry`
+    unsafe {
+        libc::getgrgid_r(
+            gid,
+            entry.as_mut_ptr(),
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        );
+
+        if result.is_null() {
+            return None;
+        }
+        Some(
+            CStr::from_ptr((*result).gr_name)
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+/// Keeps a terminal in raw mode, where key presses are read one at a time without being echoed,
+/// until it is dropped
+pub struct RawMode {
+    fd: RawFd,
+    // Terminal settings to restore when dropped
+    original: libc::termios,
+}
+
+impl RawMode {
+    /// Switches the terminal to raw mode
+    ///
+    /// # Arguments
+    ///
+    /// * `fd` - A file descriptor of the terminal
+    pub fn enable(fd: RawFd) -> Result<Self, Error> {
+        let mut settings: MaybeUninit<libc::termios> = MaybeUninit::uninit();
+
+        // Safety: `settings` is only read once tcgetattr reports it was filled in
+        let original: libc::termios = unsafe {
+            if libc::tcgetattr(fd, settings.as_mut_ptr()) != 0 {
+                return Err(Error::last_os_error());
+            }
+            settings.assume_init()
+        };
+
+        let mut raw: libc::termios = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+
+        // Safety: `raw` is a valid termios copied from the terminal's own settings
+        if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) } != 0 {
+            return Err(Error::last_os_error());
+        }
+
+        Ok(RawMode { fd, original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // Safety: `original` holds the settings read from this terminal in `enable`
+        unsafe {
+            libc::tcsetattr(self.fd, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+/// Finds the number of rows of the terminal connected to stdout
+///
+/// # Return value
+///
+/// The terminal's height, or `None` if stdout is not a terminal
+pub fn terminal_height() -> Option<usize> {
+    let mut size: MaybeUninit<libc::winsize> = MaybeUninit::uninit();
+
+    // Safety: `size` is only read once the ioctl reports it was filled in
+    unsafe {
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, size.as_mut_ptr()) != 0 {
+            return None;
+        }
+        Some(size.assume_init().ws_row as usize).filter(|rows| *rows > 0)
+    }
+}
@@ -0,0 +1,99 @@
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs::{create_dir_all, remove_dir_all, set_permissions, write, Permissions};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Mutex, MutexGuard};
+
+// The environment is shared by the whole process, so tests that change it or depend on it take
+// turns
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// Holds the environment for one test, putting back every variable it set when dropped
+pub struct EnvGuard {
+    // Each variable that was set, with its value before
+    originals: Vec<(String, Option<OsString>)>,
+    // The working directory before the test changed it
+    original_directory: Option<PathBuf>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl EnvGuard {
+    /// Waits until no other test holds the environment
+    pub fn lock() -> Self {
+        EnvGuard {
+            originals: Vec::new(),
+            original_directory: None,
+            _lock: ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+
+    /// Sets a variable until the guard is dropped
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable
+    /// * `value` - Its value for the test
+    pub fn set<V: AsRef<OsStr>>(&mut self, name: &str, value: V) {
+        if !self.originals.iter().any(|(set, _)| set == name) {
+            self.originals.push((name.to_string(), env::var_os(name)));
+        }
+        env::set_var(name, value);
+    }
+
+    /// Changes the working directory until the guard is dropped
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The working directory for the test
+    pub fn set_current_dir<P: AsRef<Path>>(&mut self, directory: P) {
+        if self.original_directory.is_none() {
+            self.original_directory = Some(env::current_dir().unwrap());
+        }
+        env::set_current_dir(directory).unwrap();
+    }
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        for (name, original) in self.originals.drain(..).rev() {
+            match original {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+        if let Some(directory) = self.original_directory.take() {
+            let _ = env::set_current_dir(directory);
+        }
+    }
+}
+
+/// Creates an empty directory for a test, replacing any left by an earlier run
+///
+/// # Arguments
+///
+/// * `name` - A name for the directory that no other test uses
+pub fn temp_directory(name: &str) -> PathBuf {
+    let directory: PathBuf = env::temp_dir().join(format!("gecko-{}-{}", name, process::id()));
+    let _ = remove_dir_all(&directory);
+    create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// Creates a directory holding an executable file for each of the given commands
+///
+/// # Arguments
+///
+/// * `name` - A name for the directory that no other test uses
+/// * `commands` - The names of the commands
+pub fn command_directory(name: &str, commands: &[&str]) -> PathBuf {
+    let directory: PathBuf = temp_directory(name);
+
+    for command in commands {
+        let file: PathBuf = directory.join(command);
+        write(&file, "").unwrap();
+        set_permissions(&file, Permissions::from_mode(0o755)).unwrap();
+    }
+    directory
+}
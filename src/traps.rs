@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+
+/// Signals that commands can be registered for, with their numbers.
+/// EXIT is not a real signal; its command runs when the shell exits.
+pub const TRAPPABLE_SIGNALS: [(i32, &str); 2] = [(0, "EXIT"), (2, "INT")];
+
+pub struct Traps {
+    // Signal names mapped to the command to run when the signal is received
+    commands: HashMap<&'static str, String>,
+}
+
+impl Default for Traps {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Traps {
+    pub fn new() -> Self {
+        Traps {
+            commands: HashMap::new(),
+        }
+    }
+
+    /// Registers a command to run when a signal is received, replacing any previous command
+    ///
+    /// # Arguments
+    ///
+    /// * `signal` - The signal's name (with or without "SIG") or number
+    /// * `command` - The command line to run
+    pub fn set(&mut self, signal: &str, command: &str) -> Result<(), Error> {
+        self.commands
+            .insert(signal_name(signal)?, command.to_string());
+        Ok(())
+    }
+
+    /// Removes the command registered for a signal
+    ///
+    /// # Arguments
+    ///
+    /// * `signal` - The signal's name (with or without "SIG") or number
+    pub fn remove(&mut self, signal: &str) -> Result<(), Error> {
+        self.commands.remove(signal_name(signal)?);
+        Ok(())
+    }
+
+    /// Gets the command registered for a signal
+    ///
+    /// # Arguments
+    ///
+    /// * `signal` - The signal's name, as listed in `TRAPPABLE_SIGNALS`
+    pub fn get(&self, signal: &str) -> Option<&String> {
+        self.commands.get(signal)
+    }
+
+    /// Prints every registered command in the form it would be registered with
+    pub fn display(&self) {
+        for (_, name) in TRAPPABLE_SIGNALS.iter() {
+            if let Some(command) = self.commands.get(name) {
+                println!("trap -- \"{}\" {}", command, name);
+            }
+        }
+    }
+}
+
+/// Finds the name of a signal that commands can be registered for
+///
+/// # Arguments
+///
+/// * `signal` - The signal's name (with or without "SIG") or number
+fn signal_name(signal: &str) -> Result<&'static str, Error> {
+    let name: &str = signal.strip_prefix("SIG").unwrap_or(signal);
+
+    TRAPPABLE_SIGNALS
+        .iter()
+        .find(|(number, known_name)| *known_name == name || number.to_string() == name)
+        .map(|(_, known_name)| *known_name)
+        .ok_or_else(|| {
+            let signals: Vec<&str> = TRAPPABLE_SIGNALS.iter().map(|(_, name)| *name).collect();
+            let error_message: String = format!(
+                "{}: invalid signal specification; only {} can be trapped",
+                signal,
+                signals.join(" and ")
+            );
+            Error::new(ErrorKind::InvalidInput, error_message)
+        })
+}
@@ -0,0 +1,291 @@
+use std::env;
+use std::fs::{
+    copy, create_dir, create_dir_all, read_dir, read_link, remove_dir_all, remove_file, rename,
+    symlink_metadata, Metadata,
+};
+use std::io::{Error, ErrorKind};
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// How to reverse a destructive builtin
+pub enum UndoAction {
+    // Files that 'touch' created, with when each was last modified, which are removed again if they
+    // are still empty and unchanged
+    RemoveCreated(Vec<(PathBuf, SystemTime)>),
+    // Files that 'rm' moved to the trash, with where each was moved from, which are moved back
+    Restore(Vec<(PathBuf, PathBuf)>),
+}
+
+pub struct UndoLog {
+    // Actions reversing each destructive builtin, most recent last
+    actions: Vec<UndoAction>,
+}
+
+impl Default for UndoLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UndoLog {
+    pub fn new() -> Self {
+        UndoLog {
+            actions: Vec::new(),
+        }
+    }
+
+    /// Records how to reverse a builtin that has just run
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - The action that reverses the builtin
+    pub fn record(&mut self, action: UndoAction) {
+        self.actions.push(action);
+    }
+
+    /// Forgets every action, once a builtin has run that cannot be reversed
+    pub fn clear(&mut self) {
+        self.actions.clear();
+    }
+
+    /// Reverses the most recent destructive builtin that has not been undone. If a file cannot be
+    /// put back, the files not yet put back are kept so 'undo' can be tried again.
+    pub fn undo(&mut self) -> Result<(), Error> {
+        let action: UndoAction = self
+            .actions
+            .pop()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "Nothing to undo"))?;
+
+        match action {
+            UndoAction::RemoveCreated(files) => {
+                for (index, (file, modified)) in files.iter().enumerate() {
+                    if let Err(e) = remove_created(file, *modified) {
+                        self.record(UndoAction::RemoveCreated(files[index..].to_vec()));
+                        return Err(e);
+                    }
+                }
+            }
+            UndoAction::Restore(files) => {
+                for (index, (trashed, original)) in files.iter().enumerate() {
+                    if let Err(e) = restore(trashed, original) {
+                        self.record(UndoAction::Restore(files[index..].to_vec()));
+                        return Err(e);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Removes a file that 'touch' created, unless it has been changed since, so nothing written to
+/// it is lost
+///
+/// # Arguments
+///
+/// * `file` - The file to remove
+/// * `modified` - When the file was last modified, as 'touch' left it
+fn remove_created(file: &Path, modified: SystemTime) -> Result<(), Error> {
+    let file_metadata: Metadata = match symlink_metadata(file) {
+        Ok(file_metadata) => file_metadata,
+        // A file that has already been removed has nothing left to undo
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    if file_metadata.len() != 0 || file_metadata.modified()? != modified {
+        println!(
+            "Kept {}, which has changed since it was created",
+            file.display()
+        );
+        return Ok(());
+    }
+
+    remove_file(file)?;
+    println!("Removed {}", file.display());
+    Ok(())
+}
+
+/// Moves a file that 'rm' moved to the trash back to where it was removed from
+///
+/// # Arguments
+///
+/// * `trashed` - Where the file is in the trash
+/// * `original` - Where the file was removed from
+fn restore(trashed: &Path, original: &Path) -> Result<(), Error> {
+    // A file made since the original was removed is not replaced
+    if symlink_metadata(original).is_ok() {
+        let error_message: String = original.display().to_string() + " already exists";
+        return Err(Error::new(ErrorKind::AlreadyExists, error_message));
+    }
+
+    move_path(trashed, original)?;
+    println!("Restored {}", original.display());
+    Ok(())
+}
+
+/// Finds the directory removed files are moved to, `~/.gecko_trash`
+fn trash_directory() -> PathBuf {
+    let home: String = env::var("HOME").unwrap_or_else(|_| String::from("."));
+    PathBuf::from(home).join(".gecko_trash")
+}
+
+/// Moves a file or directory into the trash, keeping its name unless the trash already holds
+/// something with that name, in which case a number is added to the end
+///
+/// # Arguments
+///
+/// * `path` - The file or directory to move
+///
+/// # Return value
+///
+/// Where the file or directory was moved to
+pub fn move_to_trash(path: &Path) -> Result<PathBuf, Error> {
+    let trash: PathBuf = trash_directory();
+    create_dir_all(&trash)?;
+
+    let name: String = path
+        .file_name()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Cannot remove '.' or '..'"))?
+        .to_string_lossy()
+        .to_string();
+
+    // The first of name, name.1, name.2, ... that is not in the trash
+    let mut trashed: PathBuf = trash.join(&name);
+    let mut count: usize = 1;
+    while symlink_metadata(&trashed).is_ok() {
+        trashed = trash.join(format!("{}.{}", name, count));
+        count += 1;
+    }
+
+    move_path(path, &trashed)?;
+    Ok(trashed)
+}
+
+/// Permanently removes everything in the trash
+pub fn empty_trash() -> Result<(), Error> {
+    match remove_dir_all(trash_directory()) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Moves a file or directory, copying it when it is moved to a different filesystem
+///
+/// # Arguments
+///
+/// * `from` - The file or directory to move
+/// * `to` - Where to move it to
+fn move_path(from: &Path, to: &Path) -> Result<(), Error> {
+    match rename(from, to) {
+        // Renaming only works within a filesystem
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {
+            copy_path(from, to)?;
+
+            if symlink_metadata(from)?.is_dir() {
+                remove_dir_all(from)
+            } else {
+                remove_file(from)
+            }
+        }
+        result => result,
+    }
+}
+
+/// Recursively copies a file or directory, copying symlinks as links
+///
+/// # Arguments
+///
+/// * `from` - The file or directory to copy
+/// * `to` - Where to copy it to
+fn copy_path(from: &Path, to: &Path) -> Result<(), Error> {
+    let from_metadata: Metadata = symlink_metadata(from)?;
+
+    if from_metadata.is_symlink() {
+        symlink(read_link(from)?, to)
+    } else if from_metadata.is_dir() {
+        create_dir(to)?;
+        for entry in read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        copy(from, to).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_directory, EnvGuard};
+    use std::fs::{read_to_string, write};
+
+    #[test]
+    fn failed_restore_keeps_the_rest_for_another_undo() {
+        let mut env: EnvGuard = EnvGuard::lock();
+        let directory: PathBuf = temp_directory("undo-retry");
+        env.set("HOME", &directory);
+
+        let (first, second): (PathBuf, PathBuf) =
+            (directory.join("first"), directory.join("second"));
+        write(&first, "1").unwrap();
+        write(&second, "2").unwrap();
+        let mut undo_log: UndoLog = UndoLog::new();
+        undo_log.record(UndoAction::Restore(vec![
+            (move_to_trash(&first).unwrap(), first.clone()),
+            (move_to_trash(&second).unwrap(), second.clone()),
+        ]));
+
+        // A new file in the way stops the second restore, but not the first
+        write(&second, "new").unwrap();
+        let error: Error = undo_log.undo().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+        assert_eq!(read_to_string(&first).unwrap(), "1");
+
+        remove_file(&second).unwrap();
+        undo_log.undo().unwrap();
+        assert_eq!(read_to_string(&second).unwrap(), "2");
+        assert!(undo_log.undo().is_err());
+    }
+
+    #[test]
+    fn trashed_files_with_the_same_name_are_numbered() {
+        let mut env: EnvGuard = EnvGuard::lock();
+        let directory: PathBuf = temp_directory("trash-names");
+        env.set("HOME", &directory);
+
+        let notes: PathBuf = directory.join("notes");
+        write(&notes, "first").unwrap();
+        let first: PathBuf = move_to_trash(&notes).unwrap();
+        write(&notes, "second").unwrap();
+        let second: PathBuf = move_to_trash(&notes).unwrap();
+
+        assert_eq!(first, trash_directory().join("notes"));
+        assert_eq!(second, trash_directory().join("notes.1"));
+        assert!(!notes.exists());
+
+        // Both can still be recovered
+        assert_eq!(read_to_string(&first).unwrap(), "first");
+        assert_eq!(read_to_string(&second).unwrap(), "second");
+    }
+
+    #[test]
+    fn emptying_the_trash_removes_everything_in_it() {
+        let mut env: EnvGuard = EnvGuard::lock();
+        let directory: PathBuf = temp_directory("trash-empty");
+        env.set("HOME", &directory);
+
+        let notes: PathBuf = directory.join("notes");
+        write(&notes, "notes").unwrap();
+        let trashed: PathBuf = move_to_trash(&notes).unwrap();
+
+        empty_trash().unwrap();
+        assert!(!trashed.exists());
+        assert!(!trash_directory().exists());
+
+        // There is nothing to do when the trash is already empty
+        empty_trash().unwrap();
+    }
+}
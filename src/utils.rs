@@ -1,29 +1,229 @@
+use crate::config::Config;
+use crate::jobs::Jobs;
+use crate::options::ShellOptions;
 use crate::parser::parse;
-use crate::redirect::redirect;
-use std::io::{stdin, stdout, Error, Write};
+use crate::path_cache::PathCache;
+use crate::redirect::{background_process_group, finish_redirects, redirect, OutputState};
+use std::backtrace::Backtrace;
+use std::env;
+use std::ffi::OsStr;
+use std::io::{stdin, stdout, BufRead, Error, ErrorKind, IsTerminal, Write};
+use std::iter::once;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::Path;
 use std::process::Command;
-use std::process::{id, Child, Output};
+use std::process::{id, Child, ExitStatus, Output, Stdio};
+use std::sync::Mutex;
+
+/// Every redirection, pipe, separator, and chaining operator the line parser splits commands on
+const OPERATORS: [&str; 16] = [
+    ">", ">>", "1>", "2>", "2>>", "2>&1", "&>", "&>>", "<", "<<", "<<<", "|", "&", "&&", "||", ";",
+];
+
+/// PIDs of the processes of the line running in the foreground, which Ctrl-C interrupts
+static FOREGROUND_PIDS: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// Exit status of the last command that ran, which the shell exits with by default
+static LAST_STATUS: Mutex<i32> = Mutex::new(0);
 
 /// A simple wrapper that displays a prompt and reads a line of input from the user.
 ///
+/// # Arguments
+///
+/// * `interactive` - Whether the shell is reading from a terminal; the prompt is only shown if so
+/// * `show_title` - Whether to show the current directory in the terminal's title
+/// * `separator` - The token that separates commands like ';'
+///
 /// # Return value
 ///
-/// A vector of strings corresponding to the data entered into the command line
-pub fn prompt_and_read() -> Option<Vec<String>> {
-    print!("({}) $ ", id());
-    stdout().flush().expect("Error flushing stdout");
+/// A vector of strings corresponding to the data entered into the command line, or `None` once
+/// input has ended or can no longer be read
+pub fn prompt_and_read(
+    interactive: bool,
+    show_title: bool,
+    separator: &str,
+) -> Option<Vec<String>> {
+    if show_title {
+        if let Ok(directory) = env::current_dir() {
+            set_terminal_title(&directory.display().to_string());
+        }
+    }
+    if interactive {
+        print_prompt();
+    }
     let mut buffer = String::new();
 
     match stdin().read_line(&mut buffer) {
+        // End of input, such as Ctrl-D or the end of a piped script
+        Ok(0) => {
+            if interactive {
+                println!();
+            }
+            None
+        }
         Ok(_) => {
-            let tokens = parse(buffer);
-            return Some(tokens);
+            let mut tokens = parse(buffer, separator);
+
+            // A heredoc's body is on the lines that follow, and the line is dropped without it
+            if let Err(e) = read_heredocs(&mut tokens, interactive, &mut stdin().lock()) {
+                eprintln!("Error taking user input\n{}", e);
+                return Some(Vec::new());
+            }
+            Some(tokens)
         }
+        // Ctrl-C while reading leaves a fresh prompt
+        Err(e) if e.kind() == ErrorKind::Interrupted => Some(Vec::new()),
         Err(e) => {
             eprintln!("Error taking user input\n{}", e);
-            return None;
+            None
         }
-    };
+    }
+}
+
+/// Reads the body of each heredoc on a line, from the lines after it up to the one holding only
+/// its delimiter, and puts the body in place of the delimiter. The body ends without the newline
+/// of its last line.
+///
+/// # Arguments
+///
+/// * `tokens` - The line, where each '<<' is followed by its delimiter
+/// * `interactive` - Whether the shell is reading from a terminal; a prompt is shown for each line
+///   of the body if so
+/// * `input` - Where the lines of the body are read from
+fn read_heredocs<R: BufRead>(
+    tokens: &mut [String],
+    interactive: bool,
+    input: &mut R,
+) -> Result<(), Error> {
+    for i in 1..tokens.len() {
+        if tokens[i - 1] != "<<" {
+            continue;
+        }
+
+        let mut lines: Vec<String> = Vec::new();
+        loop {
+            if interactive {
+                print!("> ");
+                stdout().flush()?;
+            }
+
+            // The body also ends where the input does
+            let mut line: String = String::new();
+            if input.read_line(&mut line)? == 0 {
+                break;
+            }
+
+            let line: &str = line.trim_end_matches(['\n', '\r']);
+            if line == tokens[i] {
+                break;
+            }
+            lines.push(line.to_string());
+        }
+
+        // Wildcards in the body are escaped so they are not expanded
+        tokens[i] = lines.join("\n").replace('*', "\\*").replace('?', "\\?");
+    }
+    Ok(())
+}
+
+/// Displays the prompt
+pub fn print_prompt() {
+    print!("({}) $ ", id());
+    stdout().flush().expect("Error flushing stdout");
+}
+
+/// Prints an error in the form the 'error_verbosity' setting asks for: one uncolored line when
+/// "quiet", the colored message and error when "normal", and those followed by the kind of error
+/// and a backtrace when "debug"
+///
+/// # Arguments
+///
+/// * `message` - What could not be done, such as "Could not list contents"
+/// * `e` - The error that stopped it
+/// * `config` - The shell's settings
+pub fn print_error(message: &str, e: &Error, config: &Config) {
+    match config.get("error_verbosity").as_str() {
+        "quiet" => eprintln!("{}: {}", message, e),
+        "debug" => eprintln!(
+            "\x1b[38;2;{}mError: {}\n{}\nKind: {:?}\n{}\x1b[0m",
+            &config.get("error_text_color"),
+            message,
+            e,
+            e.kind(),
+            Backtrace::force_capture()
+        ),
+        _ => eprintln!(
+            "\x1b[38;2;{}mError: {}\n{}\x1b[0m",
+            &config.get("error_text_color"),
+            message,
+            e
+        ),
+    }
+}
+
+/// Sets the terminal's title with the OSC 0 escape sequence. Nothing is written if stdout is not
+/// a terminal, so output that is piped or redirected is left as it is.
+///
+/// # Arguments
+///
+/// * `title` - The text of the title
+pub fn set_terminal_title(title: &str) {
+    if !stdout().is_terminal() {
+        return;
+    }
+
+    // Control characters would end the sequence early
+    let title: String = title.chars().filter(|c| !c.is_control()).collect();
+
+    print!("\x1b]0;{}\x07", title);
+    stdout().flush().expect("Error flushing stdout");
+}
+
+/// Sends SIGINT to the processes of the line running in the foreground, if any, that are outside
+/// the shell's process group. Those inside it have already been sent SIGINT by the terminal, and
+/// a second one would cut short a program that handles the first.
+///
+/// # Return value
+///
+/// True if a line was running, else false
+pub fn interrupt_foreground() -> bool {
+    let pids = FOREGROUND_PIDS.lock().unwrap_or_else(|e| e.into_inner());
+
+    // SAFETY: getpgrp and getpgid only read process groups, and kill only sends a signal; a
+    // process that has already exited is ignored
+    unsafe {
+        let shell_group: libc::pid_t = libc::getpgrp();
+
+        for pid in pids.iter() {
+            if libc::getpgid(*pid as libc::pid_t) != shell_group {
+                libc::kill(*pid as libc::pid_t, libc::SIGINT);
+            }
+        }
+    }
+    !pids.is_empty()
+}
+
+/// Gets the exit status of the last command that ran
+pub fn last_status() -> i32 {
+    *LAST_STATUS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Records the exit status of the last command that ran
+///
+/// # Arguments
+///
+/// * `status` - The command's exit status
+pub fn set_last_status(status: i32) {
+    *LAST_STATUS.lock().unwrap_or_else(|e| e.into_inner()) = status;
+}
+
+/// Records the processes of the line running in the foreground
+///
+/// # Arguments
+///
+/// * `pids` - The PIDs of the processes, or none once the line has finished
+fn set_foreground(pids: Vec<u32>) {
+    *FOREGROUND_PIDS.lock().unwrap_or_else(|e| e.into_inner()) = pids;
 }
 
 /// Executes the process and displays output to stdout and stderr
@@ -31,41 +231,485 @@ pub fn prompt_and_read() -> Option<Vec<String>> {
 /// # Arguments
 ///
 /// * `process` - A `Command` to be executed
-pub fn execute(process: &mut Command) -> Result<(), Error> {
+/// * `stages` - The already running processes piping into `process`, in pipeline order
+/// * `options` - The shell's options
+/// * `quiet` - Whether to leave out the line reporting the exit status
+/// * `show_title` - Whether to show the command in the terminal's title while it runs
+///
+/// # Return value
+///
+/// The exit code of the process, or of the pipeline it ends
+pub fn execute(
+    process: &mut Command,
+    stages: Vec<Child>,
+    options: &ShellOptions,
+    quiet: bool,
+    show_title: bool,
+) -> Result<i32, Error> {
+    if show_title {
+        // The program is shown by name, not by where it was found on PATH
+        let program: &OsStr = process.get_program();
+        let program: &OsStr = Path::new(program).file_name().unwrap_or(program);
+
+        let command: Vec<String> = once(program)
+            .chain(process.get_args())
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect();
+        set_terminal_title(&command.join(" "));
+    }
+
     // Child process
     let child: Child = match process.spawn() {
-        Ok(ok) => ok,
-        Err(e) => return Err(e),
+        Ok(child) => child,
+        Err(e) => {
+            // Closing the pipe into the process lets the stage writing to it finish
+            process.stdin(Stdio::null());
+            abandon_pipeline(stages);
+            return Err(e);
+        }
     };
 
     // The PID of the child process
     let child_id: &u32 = &child.id();
 
+    // Ctrl-C interrupts every process of the pipeline while it runs
+    set_foreground(
+        stages
+            .iter()
+            .map(Child::id)
+            .chain(Some(*child_id))
+            .collect(),
+    );
+
+    // Exit status of every stage in the pipeline, ending with the child process
+    let statuses: Result<Vec<ExitStatus>, Error> = wait_for_pipeline(child, stages);
+
+    set_foreground(Vec::new());
+    let statuses: Vec<ExitStatus> = statuses?;
+
+    // Exit code of the whole pipeline
+    let status: i32 = exit_code(pipeline_status(&statuses, options.pipefail()));
+
+    if !quiet {
+        println!("Child {} exited with status {}", child_id, status);
+    }
+
+    Ok(status)
+}
+
+/// Waits for every process of a pipeline to finish
+///
+/// # Arguments
+///
+/// * `child` - The last process of the pipeline
+/// * `stages` - The processes piping into `child`, in pipeline order
+///
+/// # Return value
+///
+/// The exit status of every process, in pipeline order
+fn wait_for_pipeline(child: Child, stages: Vec<Child>) -> Result<Vec<ExitStatus>, Error> {
     // Output of the child process
-    let child_output: Output = match child.wait_with_output() {
-        Ok(ok) => ok,
-        Err(e) => return Err(e),
+    let child_output: Output = child.wait_with_output()?;
+
+    let mut statuses: Vec<ExitStatus> = Vec::new();
+    for mut stage in stages {
+        statuses.push(stage.wait()?);
+    }
+    statuses.push(child_output.status);
+
+    Ok(statuses)
+}
+
+/// Starts the process without waiting for it, so the prompt returns straight away
+///
+/// # Arguments
+///
+/// * `process` - A `Command` to be executed
+/// * `stages` - The already running processes piping into `process`, in pipeline order
+/// * `jobs` - The background jobs, which the started processes are added to
+/// * `line` - The line being run, as it was entered
+pub fn execute_in_background(
+    process: &mut Command,
+    mut stages: Vec<Child>,
+    jobs: &mut Jobs,
+    line: &str,
+) -> Result<(), Error> {
+    // Ctrl-C at the terminal is only for the foreground
+    process.process_group(background_process_group(&stages));
+
+    // Child process
+    let child: Child = match process.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            // Closing the pipe into the process lets the stage writing to it finish
+            process.stdin(Stdio::null());
+            abandon_pipeline(stages);
+            return Err(e);
+        }
     };
 
-    println!(
-        "Child {} exited with status {}",
-        child_id, child_output.status
-    );
+    // The PID of the child process
+    let child_id: u32 = child.id();
+
+    // The processes are kept so they are waited on once finished, rather than left as zombies
+    stages.push(child);
+    let job_id: usize = jobs.add(stages, line.to_string());
+
+    println!("[{}] {}", job_id, child_id);
 
     Ok(())
 }
 
+/// Waits for the started stages of a pipeline that could not be finished, so they are not left as
+/// zombies. Nothing may still hold the pipe out of the last stage, or it may never finish.
+///
+/// # Arguments
+///
+/// * `stages` - The already running processes of the pipeline, in pipeline order
+pub fn abandon_pipeline(stages: Vec<Child>) {
+    for mut stage in stages {
+        // A stage that cannot be waited for has already been reaped
+        let _ = stage.wait();
+    }
+}
+
+/// Converts a process's exit status into a shell exit code
+///
+/// # Arguments
+///
+/// * `status` - The exit status of a finished process
+///
+/// # Return value
+///
+/// The process's exit code, or 128 plus the signal number if it was killed by a signal
+pub fn exit_code(status: ExitStatus) -> i32 {
+    match status.code() {
+        Some(code) => code,
+        None => 128 + status.signal().unwrap_or(0),
+    }
+}
+
+/// Determines the exit status of a pipeline
+///
+/// # Arguments
+///
+/// * `statuses` - The exit status of every stage of the pipeline, in pipeline order
+/// * `pipefail` - Whether any failed stage fails the pipeline
+///
+/// # Return value
+///
+/// The last stage's status, or with `pipefail` the rightmost failed stage's status if any failed
+fn pipeline_status(statuses: &[ExitStatus], pipefail: bool) -> ExitStatus {
+    // The last stage's status is the pipeline's status by default
+    let last_status: ExitStatus = statuses[statuses.len() - 1];
+
+    if pipefail {
+        statuses
+            .iter()
+            .rev()
+            .find(|status| !status.success())
+            .copied()
+            .unwrap_or(last_status)
+    } else {
+        last_status
+    }
+}
+
+/// Replaces a `~` that is a whole token, or starts a token with `~/`, with the home directory.
+/// Tokens are left as they are if HOME is not set.
+///
+/// # Arguments
+///
+/// * `tokens` - The parsed line
+///
+/// # Return value
+///
+/// The tokens with every leading `~` expanded
+pub fn expand_tilde(tokens: &[String]) -> Vec<String> {
+    let home: String = match env::var("HOME") {
+        Ok(home) => home,
+        Err(_) => return tokens.to_vec(),
+    };
+
+    tokens
+        .iter()
+        .map(|token| {
+            if token == "~" {
+                home.clone()
+            } else if let Some(rest) = token.strip_prefix("~/") {
+                home.clone() + "/" + rest
+            } else {
+                token.clone()
+            }
+        })
+        .collect()
+}
+
+/// Replaces the environment variables in each token with their values. `$NAME` and `${NAME}` are
+/// replaced by the variable's value, or nothing if it is not set, `$$` by the shell's PID, and `$?`
+/// by the exit status of the last command.
+///
+/// # Arguments
+///
+/// * `tokens` - The parsed line
+///
+/// # Return value
+///
+/// The tokens with every variable expanded
+pub fn expand_variables(tokens: &[String]) -> Vec<String> {
+    tokens.iter().map(|token| expand_token(token)).collect()
+}
+
+/// Replaces the environment variables in a single token with their values
+///
+/// # Arguments
+///
+/// * `token` - The token to expand
+fn expand_token(token: &str) -> String {
+    let mut expanded: String = String::new();
+    let mut chars = token.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+
+        match chars.peek().copied() {
+            // The shell's PID
+            Some('$') => {
+                chars.next();
+                expanded.push_str(&id().to_string());
+            }
+            // The exit status of the last command
+            Some('?') => {
+                chars.next();
+                expanded.push_str(&last_status().to_string());
+            }
+            // The same in quotes, where the '?' was escaped so it is not matched against files
+            Some('\\') if chars.clone().nth(1) == Some('?') => {
+                chars.nth(1);
+                expanded.push_str(&last_status().to_string());
+            }
+            // A name in braces, kept as typed if the braces are not closed
+            Some('{') => {
+                chars.next();
+                let mut name: String = String::new();
+                let mut closed: bool = false;
+                for next in chars.by_ref() {
+                    if next == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(next);
+                }
+
+                if closed {
+                    expanded.push_str(&env::var(&name).unwrap_or_default());
+                } else {
+                    expanded.push_str(&("${".to_owned() + &name));
+                }
+            }
+            // A name made of letters, digits, and underscores that does not start with a digit
+            Some(next) if next.is_ascii_alphabetic() || next == '_' => {
+                let mut name: String = String::new();
+                while let Some(next) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(next);
+                }
+                expanded.push_str(&env::var(&name).unwrap_or_default());
+            }
+            // A '$' that does not start a variable is kept
+            _ => expanded.push('$'),
+        }
+    }
+    expanded
+}
+
+/// Replaces each token holding an unescaped `*` or `?` with the sorted names of the files it
+/// matches. A token that matches nothing is kept as typed, and `\*` and `\?` are literal.
+///
+/// # Arguments
+///
+/// * `tokens` - The parsed line
+///
+/// # Return value
+///
+/// The tokens with every wildcard expanded
+pub fn expand_globs(tokens: &[String]) -> Vec<String> {
+    let mut expanded: Vec<String> = Vec::new();
+
+    for token in tokens {
+        let mut matches: Vec<String> = if has_wildcard(token) {
+            glob_matches(token)
+        } else {
+            Vec::new()
+        };
+
+        if matches.is_empty() {
+            expanded.push(unescape_wildcards(token));
+        } else {
+            expanded.append(&mut matches);
+        }
+    }
+    expanded
+}
+
+/// Checks whether a token holds a `*` or `?` that is not escaped with a backslash
+///
+/// # Arguments
+///
+/// * `token` - The token to check
+pub fn has_wildcard(token: &str) -> bool {
+    let pattern: Vec<char> = token.chars().collect();
+
+    pattern
+        .iter()
+        .enumerate()
+        .any(|(i, c)| (*c == '*' || *c == '?') && (i == 0 || pattern[i - 1] != '\\'))
+}
+
+/// Finds the files matching a pattern. Wildcards are only matched in the last part of the path,
+/// and a name starting with '.' is only matched by a pattern starting with '.'.
+///
+/// # Arguments
+///
+/// * `pattern` - The token holding wildcards
+///
+/// # Return value
+///
+/// The paths of the matching files, in the form they were typed, sorted
+pub fn glob_matches(pattern: &str) -> Vec<String> {
+    // The directory to search and the pattern for names in it
+    let (prefix, name_pattern): (&str, &str) = match pattern.rfind('/') {
+        Some(index) => pattern.split_at(index + 1),
+        None => ("", pattern),
+    };
+    let directory: String = unescape_wildcards(prefix);
+
+    let entries = match std::fs::read_dir(if directory.is_empty() {
+        "."
+    } else {
+        &directory
+    }) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let name_pattern: Vec<char> = name_pattern.chars().collect();
+    let mut matches: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| !name.starts_with('.') || name_pattern.first() == Some(&'.'))
+        .filter(|name| wildcard_match(&name_pattern, &name.chars().collect::<Vec<char>>()))
+        .map(|name| directory.clone() + &name)
+        .collect();
+    matches.sort();
+    matches
+}
+
+/// Checks whether a name matches a pattern, where `*` matches any characters, `?` matches one
+/// character, and `\` makes the character after it literal
+///
+/// # Arguments
+///
+/// * `pattern` - The pattern to match
+/// * `name` - The name to check
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    let mut p: usize = 0;
+    let mut n: usize = 0;
+    // The pattern after the last '*' seen, and where in the name the '*' stopped matching
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        // How much of the pattern matches the next character of the name, if it matches
+        let matched: Option<usize> = match pattern.get(p) {
+            Some('*') => {
+                // Let the '*' match nothing at first
+                star = Some((p + 1, n));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('\\') if p + 1 < pattern.len() => (pattern[p + 1] == name[n]).then_some(2),
+            Some(c) => (*c == name[n]).then_some(1),
+            None => None,
+        };
+
+        match (matched, star) {
+            (Some(length), _) => {
+                p += length;
+                n += 1;
+            }
+            // Let the last '*' match one more character and try the rest of the pattern again
+            (None, Some((after_star, star_end))) => {
+                star = Some((after_star, star_end + 1));
+                p = after_star;
+                n = star_end + 1;
+            }
+            (None, None) => return false,
+        }
+    }
+
+    // The name is used up, so only '*'s that match nothing can be left
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Removes the backslashes escaping wildcards
+///
+/// # Arguments
+///
+/// * `token` - The token to unescape
+pub fn unescape_wildcards(token: &str) -> String {
+    token.replace("\\*", "*").replace("\\?", "?")
+}
+
+/// Parses the line of user input
+///
+/// # Arguments
+///
+/// * `tokens` - A slice of strings representing a command and its arguments
+/// * `process` - An `Option` representing a `Command` to be modified/executed/returned
+/// * `stages` - The pipeline stages that have already been started
+/// * `path_cache` - Cached locations of commands on PATH
+/// * `background` - Whether the line runs in the background
+///
+/// # Return value
+///
+/// A `Result` with an `Option` containing a ready-to-execute `Command`
+pub fn parse_line(
+    tokens: &[String],
+    process: Option<Command>,
+    stages: &mut Vec<Child>,
+    path_cache: &mut PathCache,
+    background: bool,
+) -> Result<Option<Command>, Error> {
+    // Where standard output of the last process points, for '2>&1'
+    let mut output: OutputState = OutputState::new(background);
+
+    let process: Option<Command> = parse_tokens(tokens, process, stages, path_cache, &mut output)?;
+    finish_redirects(process, &mut output)
+}
+
 /// Recursively parses the line of user input
 ///
 /// # Arguments
 ///
 /// * `tokens` - A slice of strings representing a command and its arguments
 /// * `process` - An `Option` representing a `Command` to be modified/executed/returned
+/// * `stages` - The pipeline stages that have already been started
+/// * `path_cache` - Cached locations of commands on PATH
+/// * `output` - Where standard output of the process being set up points
 ///
 /// # Return value
 ///
 /// A `Result` with an `Option` containing a ready-to-execute `Command`
-pub fn parse_line(tokens: &[String], process: Option<Command>) -> Result<Option<Command>, Error> {
+fn parse_tokens(
+    tokens: &[String],
+    process: Option<Command>,
+    stages: &mut Vec<Child>,
+    path_cache: &mut PathCache,
+    output: &mut OutputState,
+) -> Result<Option<Command>, Error> {
     // Base case of recursion; no tokens left to parse
     if tokens.is_empty() {
         return Ok(process);
@@ -82,7 +726,7 @@ pub fn parse_line(tokens: &[String], process: Option<Command>) -> Result<Option<
 
     // If there is no process to redirect, no partial output to redirect,
     // and the first character is a redirector, then an error has occurred
-    if process.is_none() && redirector != "" {
+    if process.is_none() && !redirector.is_empty() {
         eprintln!("Error: Expected program, found {}", redirector);
         return Ok(None);
     }
@@ -91,15 +735,16 @@ pub fn parse_line(tokens: &[String], process: Option<Command>) -> Result<Option<
     // list if no special character is present
     let splitter_index: usize = tokens
         .iter()
-        .position(|x| is_special(&x))
+        .position(|x| is_special(x))
         .unwrap_or(tokens.len());
     let (command, leftover) = tokens.split_at(splitter_index);
 
     // Obtain a new process by redirecting
-    let new_process: Option<Command> = redirect(redirector, command, process)?;
+    let new_process: Option<Command> =
+        redirect(redirector, command, process, stages, path_cache, output)?;
 
     // Recursively return to parse the rest of the line
-    return parse_line(leftover, new_process);
+    parse_tokens(leftover, new_process, stages, path_cache, output)
 }
 
 /// Determines if the current slice is a special token.
@@ -110,17 +755,175 @@ pub fn parse_line(tokens: &[String], process: Option<Command>) -> Result<Option<
 ///
 /// # Return value
 ///
-/// True if the specified token is "special" (i.e., is an operator like >, >>, |, <, etc); false
-/// otherwise.
-fn is_special(token: &str) -> bool {
-    if token.len() == 1
-        && (token.find("<") == Some(0)
-            || token.find(">") == Some(0)
-            || token.find("!") == Some(0)
-            || token.find("|") == Some(0))
-        || token.len() == 2 && token.rfind(">") == Some(1)
-    {
-        return true;
-    }
-    false
+/// True if the specified token is one of the `OPERATORS`; false otherwise.
+pub fn is_special(token: &str) -> bool {
+    OPERATORS.contains(&token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_directory, EnvGuard};
+    use std::fs::read_to_string;
+    use std::path::PathBuf;
+
+    #[test]
+    fn heredoc_body_replaces_the_delimiter() {
+        let mut tokens: Vec<String> = parse(String::from("cat << EOF > out\n"), ";");
+        let mut input: &[u8] = b"hello *\nworld\nEOF\nnext line\n";
+        read_heredocs(&mut tokens, false, &mut input).unwrap();
+
+        // The body ends without its last newline, with wildcards escaped, and the input after the
+        // delimiter is left for the next line
+        assert_eq!(tokens, ["cat", "<<", "hello \\*\nworld", ">", "out"]);
+        assert_eq!(input, b"next line\n");
+    }
+
+    #[test]
+    fn heredoc_body_is_fed_to_the_command() {
+        let _env: EnvGuard = EnvGuard::lock();
+        let out: PathBuf = temp_directory("heredoc").join("out");
+
+        let mut tokens: Vec<String> = parse(format!("cat << EOF > {}\n", out.display()), ";");
+        read_heredocs(&mut tokens, false, &mut &b"hello\nworld\nEOF\n"[..]).unwrap();
+        let tokens: Vec<String> = expand_globs(&tokens);
+
+        let mut stages: Vec<Child> = Vec::new();
+        let mut process: Command =
+            parse_line(&tokens, None, &mut stages, &mut PathCache::new(), false)
+                .unwrap()
+                .unwrap();
+        let status: i32 = execute(&mut process, stages, &ShellOptions::new(), true, false).unwrap();
+
+        assert_eq!(status, 0);
+        assert_eq!(read_to_string(&out).unwrap(), "hello\nworld");
+    }
+
+    #[test]
+    fn every_operator_is_special() {
+        for operator in [
+            ">", ">>", "1>", "2>", "2>>", "2>&1", "&>", "&>>", "<", "<<", "<<<", "|", "&", "&&",
+            "||", ";",
+        ] {
+            assert!(is_special(operator), "{} should be special", operator);
+        }
+    }
+
+    #[test]
+    fn other_tokens_are_not_special() {
+        for token in ["", "ls", "a>", "<file", "!", "&&&", "<<<<", ">>>", "2>&2"] {
+            assert!(!is_special(token), "{} should not be special", token);
+        }
+    }
+
+    /// Creates the exit status of a process that exited with the given code
+    fn exited(code: i32) -> ExitStatus {
+        ExitStatus::from_raw(code << 8)
+    }
+
+    #[test]
+    fn pipelines_have_the_last_status_unless_pipefail_is_set() {
+        let statuses: [ExitStatus; 3] = [exited(1), exited(2), exited(0)];
+        assert_eq!(pipeline_status(&statuses, false), exited(0));
+        assert_eq!(pipeline_status(&statuses, true), exited(2));
+
+        // A pipeline where every stage succeeds succeeds either way
+        let statuses: [ExitStatus; 2] = [exited(0), exited(0)];
+        assert_eq!(pipeline_status(&statuses, false), exited(0));
+        assert_eq!(pipeline_status(&statuses, true), exited(0));
+
+        let statuses: [ExitStatus; 2] = [exited(0), exited(3)];
+        assert_eq!(pipeline_status(&statuses, false), exited(3));
+        assert_eq!(pipeline_status(&statuses, true), exited(3));
+    }
+
+    #[test]
+    fn processes_killed_by_a_signal_exit_with_128_plus_the_signal() {
+        assert_eq!(exit_code(exited(0)), 0);
+        assert_eq!(exit_code(exited(2)), 2);
+
+        // A raw status of just the signal number means the process was killed by it
+        assert_eq!(exit_code(ExitStatus::from_raw(libc::SIGTERM)), 143);
+        assert_eq!(exit_code(ExitStatus::from_raw(libc::SIGINT)), 130);
+    }
+
+    /// Checks whether a name matches a pattern
+    fn matches(pattern: &str, name: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+        wildcard_match(&pattern, &name)
+    }
+
+    #[test]
+    fn stars_match_any_characters() {
+        assert!(matches("*", ""));
+        assert!(matches("*", "notes.txt"));
+        assert!(matches("*.txt", "notes.txt"));
+        assert!(matches("n*s*t", "notes.txt"));
+        assert!(matches("**s", "notes"));
+        assert!(!matches("*.txt", "notes.md"));
+        assert!(!matches("a*", ""));
+
+        // A pattern with many stars that cannot match fails without trying every split
+        let pattern: String = "a*".repeat(50) + "b";
+        assert!(!matches(&pattern, &"a".repeat(200)));
+    }
+
+    #[test]
+    fn question_marks_match_one_character() {
+        assert!(matches("?", "a"));
+        assert!(matches("n?tes", "notes"));
+        assert!(matches("??", "üé"));
+        assert!(!matches("?", ""));
+        assert!(!matches("?", "ab"));
+    }
+
+    #[test]
+    fn escaped_wildcards_match_only_themselves() {
+        assert!(matches(r"a\*", "a*"));
+        assert!(!matches(r"a\*", "ab"));
+        assert!(matches(r"\?", "?"));
+        assert!(!matches(r"\?", "a"));
+        assert!(matches(r"*\*", "star*"));
+        assert!(!matches(r"*\*", "star"));
+
+        // A backslash at the end of the pattern is itself
+        assert!(matches(r"a\", r"a\"));
+    }
+
+    #[test]
+    fn variables_are_replaced_by_their_values() {
+        let mut env: EnvGuard = EnvGuard::lock();
+        env.set("GECKO_TEST_NAME", "gecko");
+        env::remove_var("GECKO_TEST_UNSET");
+        set_last_status(3);
+
+        assert_eq!(expand_token("$GECKO_TEST_NAME"), "gecko");
+        assert_eq!(expand_token("${GECKO_TEST_NAME}s"), "geckos");
+        assert_eq!(expand_token("a$GECKO_TEST_NAME.rs"), "agecko.rs");
+        assert_eq!(expand_token("$$"), id().to_string());
+        assert_eq!(expand_token("$?"), "3");
+
+        // Unset variables are replaced by nothing, and a '$' that starts no name is kept
+        assert_eq!(expand_token("[$GECKO_TEST_UNSET]"), "[]");
+        assert_eq!(expand_token("${GECKO_TEST_UNSET}"), "");
+        assert_eq!(expand_token("$5 costs $"), "$5 costs $");
+        assert_eq!(expand_token("${GECKO_TEST_NAME"), "${GECKO_TEST_NAME");
+        set_last_status(0);
+    }
+
+    #[test]
+    fn only_a_leading_tilde_is_the_home_directory() {
+        let mut env: EnvGuard = EnvGuard::lock();
+        env.set("HOME", "/home/gecko");
+
+        let tokens: Vec<String> = ["~", "~/x", "a~b", "~user", "x/~"]
+            .iter()
+            .map(|token| token.to_string())
+            .collect();
+        assert_eq!(
+            expand_tilde(&tokens),
+            ["/home/gecko", "/home/gecko/x", "a~b", "~user", "x/~"]
+        );
+    }
 }